@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_homework::part_2::{self, Format, Request};
+
+const FIXTURE: &str = include_str!("../fixtures/request.json");
+
+fn request() -> Request {
+    Format::Json
+        .parse(FIXTURE)
+        .expect("fixture is a valid request")
+}
+
+fn encode(c: &mut Criterion) {
+    let request = request();
+    let json = serde_json::to_vec(&request).unwrap();
+    let msgpack = part_2::to_msgpack(&request).unwrap();
+    println!(
+        "encoded size: json {} bytes, msgpack {} bytes",
+        json.len(),
+        msgpack.len()
+    );
+
+    let mut group = c.benchmark_group("encode");
+    group.bench_function("json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&request)).unwrap())
+    });
+    group.bench_function("msgpack", |b| {
+        b.iter(|| part_2::to_msgpack(black_box(&request)).unwrap())
+    });
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let request = request();
+    let json = serde_json::to_vec(&request).unwrap();
+    let msgpack = part_2::to_msgpack(&request).unwrap();
+
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("json", |b| {
+        b.iter(|| serde_json::from_slice::<Request>(black_box(&json)).unwrap())
+    });
+    group.bench_function("msgpack", |b| {
+        b.iter(|| part_2::from_msgpack(black_box(&msgpack)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,424 @@
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use rust_homework::part_2::{
+    self, CompactDuration, Format, Redaction, RedactionPolicy, Request, RequestReader, SettingsRepr,
+};
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(author, version, about = "Tools for stream request payloads", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Read newline-delimited JSON requests and summarise the batch
+    Batch {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+    },
+    /// Convert a request between JSON, TOML and YAML
+    Convert {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+
+        /// Input format, detected from the extension or content when omitted
+        #[arg(long)]
+        from: Option<Format>,
+
+        /// Output format, taken from the output extension when omitted
+        #[arg(long)]
+        to: Option<Format>,
+
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write stream settings as the raw number or as a list of flag names
+        #[arg(long)]
+        settings: Option<SettingsRepr>,
+    },
+    /// Show the structural differences between two requests
+    Diff {
+        /// The original request
+        old: PathBuf,
+
+        /// The changed request
+        new: PathBuf,
+
+        /// Print the patch as JSON, ready for `patch`
+        #[arg(long)]
+        json: bool,
+    },
+    /// Apply a JSON patch produced by `diff --json` to a request
+    Patch {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+
+        /// Patch file
+        #[arg(long)]
+        patch: PathBuf,
+
+        /// Output format, the input format when omitted
+        #[arg(long)]
+        to: Option<Format>,
+
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Bill a stream request and print the itemised invoice
+    Invoice {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+
+        /// Input format, detected from the extension or content when omitted
+        #[arg(long)]
+        from: Option<Format>,
+
+        /// How long the stream was watched, one tariff period when omitted
+        #[arg(long)]
+        watched: Option<CompactDuration>,
+
+        /// Invoice format
+        #[arg(long, default_value = "json")]
+        to: Format,
+
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a request with identifiers and debug details redacted
+    Redact {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+
+        /// Input format, detected from the extension or content when omitted
+        #[arg(long)]
+        from: Option<Format>,
+
+        /// Output format, the input format when omitted
+        #[arg(long)]
+        to: Option<Format>,
+
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Field to replace with a hash, e.g. stream.user_id
+        #[arg(long, value_name = "FIELD")]
+        hash: Vec<String>,
+
+        /// Field to mask, e.g. stream.shard_url
+        #[arg(long, value_name = "FIELD")]
+        mask: Vec<String>,
+
+        /// Field to leave out, e.g. debug
+        #[arg(long, value_name = "FIELD")]
+        drop: Vec<String>,
+
+        /// Salt mixed into hashed values
+        #[arg(long, default_value = "")]
+        salt: String,
+    },
+    /// Write the JSON schema of the request payload
+    Schema {
+        /// Output file, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check a request for semantic problems and list all of them
+    Validate {
+        /// Input file, stdin when omitted or "-"
+        input: Option<PathBuf>,
+
+        /// Input format, detected from the extension or content when omitted
+        #[arg(long)]
+        from: Option<Format>,
+
+        /// Also reject settings bits that have no name
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
+fn read_input(path: Option<&Path>) -> Result<String> {
+    match path {
+        Some(path) if path != Path::new("-") => {
+            fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))
+        }
+        _ => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .context("Failed to read stdin")?;
+            Ok(input)
+        }
+    }
+}
+
+fn write_output(path: Option<&Path>, output: &str) -> Result<()> {
+    match path {
+        Some(path) => {
+            fs::write(path, output).with_context(|| format!("Failed to write '{}'", path.display()))
+        }
+        None => {
+            print!("{}", output);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_convert(
+    input: Option<PathBuf>,
+    from: Option<Format>,
+    to: Option<Format>,
+    output: Option<PathBuf>,
+    settings: Option<SettingsRepr>,
+) -> Result<()> {
+    let to = to
+        .or_else(|| output.as_deref().and_then(Format::from_path))
+        .context("Output format is required: pass --to or an output file with an extension")?;
+
+    let converted = match settings {
+        Some(repr) => {
+            let mut request = read_request(input.as_deref(), from)?;
+            request.set_settings_repr(repr);
+            to.render(&request)?
+        }
+        None => {
+            let from = from.or_else(|| input.as_deref().and_then(Format::from_path));
+            let text = read_input(input.as_deref())?;
+            part_2::convert(&text, from, to)?
+        }
+    };
+    write_output(output.as_deref(), &converted)
+}
+
+fn read_request(input: Option<&Path>, from: Option<Format>) -> Result<Request> {
+    read_request_as(input, from).map(|(request, _)| request)
+}
+
+fn read_request_as(input: Option<&Path>, from: Option<Format>) -> Result<(Request, Format)> {
+    let text = read_input(input)?;
+    let format = from
+        .or_else(|| input.and_then(Format::from_path))
+        .or_else(|| Format::detect(&text))
+        .context("Could not detect the input format, pass --from")?;
+    Ok((format.parse(&text)?, format))
+}
+
+fn cmd_diff(old: PathBuf, new: PathBuf, json: bool) -> Result<()> {
+    let old = read_request(Some(&old), None)?;
+    let new = read_request(Some(&new), None)?;
+    let patch = old.diff(&new)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&patch)?);
+    } else {
+        print!("{}", patch);
+    }
+    Ok(())
+}
+
+fn cmd_patch(
+    input: Option<PathBuf>,
+    patch: PathBuf,
+    to: Option<Format>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let (request, format) = read_request_as(input.as_deref(), None)?;
+    let patch: part_2::Patch = serde_json::from_str(&read_input(Some(&patch))?)
+        .with_context(|| format!("Invalid patch '{}'", patch.display()))?;
+    let patched = request.apply(&patch)?;
+    write_output(output.as_deref(), &to.unwrap_or(format).render(&patched)?)
+}
+
+fn cmd_invoice(
+    input: Option<PathBuf>,
+    from: Option<Format>,
+    watched: Option<CompactDuration>,
+    to: Format,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let request = read_request(input.as_deref(), from)?;
+    let stream = request
+        .stream()
+        .context("The request has no stream to bill")?;
+    let watched = watched.map_or(stream.effective_tariff().period, Into::into);
+    let invoice = request.invoice(watched)?;
+
+    let text = match to {
+        Format::Json => serde_json::to_string_pretty(&invoice)? + "\n",
+        Format::Toml => toml::to_string(&invoice)?,
+        Format::Yaml => serde_yaml::to_string(&invoice)?,
+    };
+    write_output(output.as_deref(), &text)
+}
+
+struct RedactArgs {
+    hash: Vec<String>,
+    mask: Vec<String>,
+    drop: Vec<String>,
+    salt: String,
+}
+
+impl RedactArgs {
+    // The standard policy applies unless fields are named explicitly.
+    fn policy(&self) -> RedactionPolicy {
+        let rules = [
+            (&self.hash, Redaction::Hash),
+            (&self.mask, Redaction::Mask),
+            (&self.drop, Redaction::Drop),
+        ];
+        let mut policy = RedactionPolicy::new();
+        for (fields, redaction) in rules {
+            for field in fields {
+                policy = policy.field(field, redaction);
+            }
+        }
+        if policy.is_empty() {
+            policy = RedactionPolicy::standard();
+        }
+        policy.with_salt(&self.salt)
+    }
+}
+
+fn cmd_redact(
+    input: Option<PathBuf>,
+    from: Option<Format>,
+    to: Option<Format>,
+    output: Option<PathBuf>,
+    args: RedactArgs,
+) -> Result<()> {
+    let (request, format) = read_request_as(input.as_deref(), from)?;
+    let policy = args.policy();
+    policy.check()?;
+    let redacted = request.redacted(&policy);
+    let text = match to.unwrap_or(format) {
+        Format::Json => serde_json::to_string_pretty(&redacted)? + "\n",
+        Format::Toml => toml::to_string(&redacted)?,
+        Format::Yaml => serde_yaml::to_string(&redacted)?,
+    };
+    write_output(output.as_deref(), &text)
+}
+
+fn cmd_validate(input: Option<PathBuf>, from: Option<Format>, strict: bool) -> Result<()> {
+    let request = read_request(input.as_deref(), from)?;
+    let result = if strict {
+        request.validate_strict()
+    } else {
+        request.validate()
+    };
+    match result {
+        Ok(()) => {
+            println!("✅ Request is valid.");
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors.0 {
+                eprintln!("- {}", error);
+            }
+            Err(anyhow::anyhow!("{} problem(s) found", errors.0.len()))
+        }
+    }
+}
+
+fn cmd_batch(input: Option<PathBuf>) -> Result<()> {
+    let summary = match input.as_deref() {
+        Some(path) if path != Path::new("-") => {
+            let file = fs::File::open(path)
+                .with_context(|| format!("Failed to read '{}'", path.display()))?;
+            summarise(BufReader::new(file))
+        }
+        _ => summarise(io::stdin().lock()),
+    };
+    print!("{}", summary);
+    if summary.is_clean() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} invalid line(s)", summary.invalid))
+    }
+}
+
+fn summarise(reader: impl io::BufRead) -> part_2::BatchSummary {
+    let mut requests = RequestReader::new(reader);
+    requests.by_ref().for_each(drop);
+    requests.into_summary()
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Batch { input } => {
+            cmd_batch(input).context("Failed to execute 'batch' command")?;
+        }
+        Commands::Convert {
+            input,
+            from,
+            to,
+            output,
+            settings,
+        } => {
+            cmd_convert(input, from, to, output, settings)
+                .context("Failed to execute 'convert' command")?;
+        }
+        Commands::Diff { old, new, json } => {
+            cmd_diff(old, new, json).context("Failed to execute 'diff' command")?;
+        }
+        Commands::Patch {
+            input,
+            patch,
+            to,
+            output,
+        } => {
+            cmd_patch(input, patch, to, output).context("Failed to execute 'patch' command")?;
+        }
+        Commands::Invoice {
+            input,
+            from,
+            watched,
+            to,
+            output,
+        } => {
+            cmd_invoice(input, from, watched, to, output)
+                .context("Failed to execute 'invoice' command")?;
+        }
+        Commands::Redact {
+            input,
+            from,
+            to,
+            output,
+            hash,
+            mask,
+            drop,
+            salt,
+        } => {
+            let args = RedactArgs {
+                hash,
+                mask,
+                drop,
+                salt,
+            };
+            cmd_redact(input, from, to, output, args)
+                .context("Failed to execute 'redact' command")?;
+        }
+        Commands::Schema { output } => {
+            write_output(output.as_deref(), &part_2::request_schema_json())
+                .context("Failed to execute 'schema' command")?;
+        }
+        Commands::Validate {
+            input,
+            from,
+            strict,
+        } => {
+            cmd_validate(input, from, strict).context("Failed to execute 'validate' command")?;
+        }
+    }
+
+    Ok(())
+}
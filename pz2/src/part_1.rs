@@ -1,4 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::marker::PhantomData;
+use uuid::Uuid;
+
+mod any_post;
+#[cfg(test)]
+mod fixture;
+mod persist;
+mod policy;
+mod retention;
+mod revision;
+mod schedule;
+mod store;
+
+pub use any_post::{AnyPost, TransitionError};
+pub use persist::{InvalidRecord, PostRecord, StateMismatch};
+pub use policy::{
+    BannedWords, ContentPolicy, MaxLength, MaxLinks, MinLength, PolicyAction, PolicyOutcome,
+    PolicyReport, PolicySet, RuleHit,
+};
+pub use retention::{RetentionPolicy, Tombstone};
+pub use revision::{DiffLine, Revision};
+pub use schedule::{Clock, ManualClock, Schedule, SystemClock};
+pub use store::{PostStore, StoreError};
+pub use typestate_macros::Typestate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateKind {
+    New,
+    Unmoderated,
+    PartiallyApproved,
+    Scheduled,
+    Published,
+    Deleted,
+}
+
+impl fmt::Display for StateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StateKind::New => "new",
+            StateKind::Unmoderated => "unmoderated",
+            StateKind::PartiallyApproved => "partially_approved",
+            StateKind::Scheduled => "scheduled",
+            StateKind::Published => "published",
+            StateKind::Deleted => "deleted",
+        };
+        f.write_str(name)
+    }
+}
+
+pub trait State {
+    const KIND: StateKind;
+}
 
 #[derive(Debug, PartialEq)]
 pub struct New;
@@ -6,88 +61,547 @@ pub struct New;
 #[derive(Debug, PartialEq)]
 pub struct Unmoderated;
 
+#[derive(Debug, PartialEq)]
+pub struct PartiallyApproved;
+
+#[derive(Debug, PartialEq)]
+pub struct Scheduled;
+
 #[derive(Debug, PartialEq)]
 pub struct Published;
 
 #[derive(Debug, PartialEq)]
 pub struct Deleted;
 
+impl State for New {
+    const KIND: StateKind = StateKind::New;
+}
+
+impl State for Unmoderated {
+    const KIND: StateKind = StateKind::Unmoderated;
+}
+
+impl State for PartiallyApproved {
+    const KIND: StateKind = StateKind::PartiallyApproved;
+}
+
+impl State for Scheduled {
+    const KIND: StateKind = StateKind::Scheduled;
+}
+
+impl State for Published {
+    const KIND: StateKind = StateKind::Published;
+}
+
+impl State for Deleted {
+    const KIND: StateKind = StateKind::Deleted;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: StateKind,
+    pub to: StateKind,
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Review {
+    pub quorum: usize,
+    pub approvals: Vec<String>,
+}
+
+// How many distinct reviewers must approve a post before it goes live. It is
+// set by configuration rather than by a reviewer, and a partially approved
+// post keeps the quorum its review started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReviewPolicy {
+    pub quorum: usize,
+}
+
+impl ReviewPolicy {
+    pub fn new(quorum: usize) -> Self {
+        ReviewPolicy { quorum }
+    }
+}
+
+impl Default for ReviewPolicy {
+    fn default() -> Self {
+        ReviewPolicy::new(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PostBody {
+    id: Uuid,
+    revisions: Vec<Revision>,
+    live: Option<usize>,
+    history: Vec<Transition>,
+    review: Option<Review>,
+    schedule: Option<Schedule>,
+}
+
 #[derive(Debug)]
 pub struct Post<State> {
-    content: String,
+    body: Box<PostBody>,
     state: PhantomData<State>,
 }
 
-impl Post<New> {
-    pub fn new(content: &str) -> Self {
+#[derive(Debug)]
+pub enum Approval {
+    Pending(Post<PartiallyApproved>),
+    Published(Post<Published>),
+}
+
+impl<S: State> Post<S> {
+    pub fn id(&self) -> Uuid {
+        self.body.id
+    }
+
+    pub fn state(&self) -> StateKind {
+        S::KIND
+    }
+
+    pub fn history(&self) -> &[Transition] {
+        &self.body.history
+    }
+
+    pub fn revisions(&self) -> &[Revision] {
+        &self.body.revisions
+    }
+
+    pub fn diff(&self, from: usize, to: usize) -> Option<Vec<DiffLine>> {
+        let from = self.body.revision(from)?;
+        let to = self.body.revision(to)?;
+        Some(revision::diff_lines(&from.content, &to.content))
+    }
+
+    fn go_live(mut self, actor: &str, at: DateTime<Utc>) -> Post<Published> {
+        self.body.live = Some(self.body.latest().number);
+        self.body.review = None;
+        self.record(actor, None, at)
+    }
+
+    fn record<T: State>(mut self, actor: &str, reason: Option<&str>, at: DateTime<Utc>) -> Post<T> {
+        self.body.history.push(Transition {
+            from: S::KIND,
+            to: T::KIND,
+            at,
+            actor: actor.to_string(),
+            reason: reason.map(str::to_string),
+        });
         Post {
-            content: content.to_string(),
+            body: self.body,
             state: PhantomData,
         }
     }
+}
+
+impl PostBody {
+    // The newest revision that was not rejected; the first one never can be.
+    fn latest(&self) -> &Revision {
+        self.revisions
+            .iter()
+            .rev()
+            .find(|r| !r.rejected)
+            .expect("a post always has at least one revision")
+    }
+
+    fn content(&self) -> &str {
+        &self.latest().content
+    }
+
+    fn revision(&self, number: usize) -> Option<&Revision> {
+        self.revisions.iter().find(|r| r.number == number)
+    }
+
+    fn live_content(&self) -> Option<&str> {
+        self.live
+            .and_then(|number| self.revision(number))
+            .map(|r| r.content.as_str())
+    }
 
-    pub fn publish(self) -> Post<Unmoderated> {
+    fn push_revision(&mut self, content: &str, at: DateTime<Utc>) {
+        self.revisions.push(Revision {
+            number: self.revisions.last().map_or(1, |r| r.number + 1),
+            content: content.to_string(),
+            created_at: at,
+            rejected: false,
+        });
+    }
+}
+
+impl Post<New> {
+    pub fn new(content: &str, clock: &impl Clock) -> Self {
+        let mut body = PostBody {
+            id: Uuid::new_v4(),
+            revisions: Vec::new(),
+            live: None,
+            history: Vec::new(),
+            review: None,
+            schedule: None,
+        };
+        body.push_revision(content, clock.now());
         Post {
-            content: self.content,
+            body: Box::new(body),
             state: PhantomData,
         }
     }
+
+    pub fn publish(self, actor: &str, clock: &impl Clock) -> Post<Unmoderated> {
+        self.record(actor, None, clock.now())
+    }
 }
 
 impl Post<Unmoderated> {
-    pub fn allow(self) -> Post<Published> {
-        Post {
-            content: self.content,
-            state: PhantomData,
+    pub fn content(&self) -> &str {
+        self.body.content()
+    }
+
+    pub fn live_content(&self) -> Option<&str> {
+        self.body.live_content()
+    }
+
+    // A single moderator's decision, refused when the policy asks for more
+    // than one approval.
+    pub fn allow(
+        self,
+        actor: &str,
+        policy: &ReviewPolicy,
+        clock: &impl Clock,
+    ) -> Result<Post<Published>, Self> {
+        if policy.quorum > 1 {
+            return Err(self);
         }
+        Ok(self.go_live(actor, clock.now()))
     }
 
-    pub fn deny(self) -> Post<Deleted> {
-        Post {
-            content: self.content,
-            state: PhantomData,
+    pub fn deny(self, actor: &str, reason: &str, clock: &impl Clock) -> Post<Deleted> {
+        self.record(actor, Some(reason), clock.now())
+    }
+
+    pub fn approve(
+        mut self,
+        reviewer: &str,
+        policy: &ReviewPolicy,
+        clock: &impl Clock,
+    ) -> Approval {
+        if policy.quorum <= 1 {
+            return Approval::Published(self.go_live(reviewer, clock.now()));
         }
+        self.body.review = Some(Review {
+            quorum: policy.quorum,
+            approvals: vec![reviewer.to_string()],
+        });
+        Approval::Pending(self.record(reviewer, None, clock.now()))
+    }
+
+    // Only a revision submitted after the live one can be rejected. It stays
+    // in the history marked as rejected, and the post falls back to what was
+    // live before.
+    pub fn reject_edit(
+        self,
+        actor: &str,
+        reason: &str,
+        clock: &impl Clock,
+    ) -> Result<Post<Published>, Self> {
+        let latest = self.body.latest().number;
+        if self.body.live.is_none_or(|live| latest <= live) {
+            return Err(self);
+        }
+        let mut post = self;
+        if let Some(revision) = post.body.revisions.iter_mut().find(|r| r.number == latest) {
+            revision.rejected = true;
+        }
+        Ok(post.record(actor, Some(reason), clock.now()))
     }
 }
 
-impl Post<Published> {
+impl Post<PartiallyApproved> {
     pub fn content(&self) -> &str {
-        &self.content
+        self.body.content()
     }
 
-    pub fn delete(self) -> Post<Deleted> {
-        Post {
-            content: self.content,
-            state: PhantomData,
+    pub fn live_content(&self) -> Option<&str> {
+        self.body.live_content()
+    }
+
+    pub fn review(&self) -> &Review {
+        self.body
+            .review
+            .as_ref()
+            .expect("partially approved post always carries a review")
+    }
+
+    pub fn approve(mut self, reviewer: &str, clock: &impl Clock) -> Approval {
+        let review = self
+            .body
+            .review
+            .as_mut()
+            .expect("partially approved post always carries a review");
+        if review.approvals.iter().any(|r| r == reviewer) {
+            return Approval::Pending(self);
+        }
+        review.approvals.push(reviewer.to_string());
+        if review.approvals.len() >= review.quorum {
+            Approval::Published(self.go_live(reviewer, clock.now()))
+        } else {
+            Approval::Pending(self.record(reviewer, None, clock.now()))
         }
     }
+
+    pub fn deny(self, reviewer: &str, reason: &str, clock: &impl Clock) -> Post<Deleted> {
+        self.record(reviewer, Some(reason), clock.now())
+    }
+}
+
+impl Post<Published> {
+    pub fn content(&self) -> &str {
+        self.body
+            .live_content()
+            .unwrap_or_else(|| self.body.content())
+    }
+
+    pub fn edit(mut self, content: &str, actor: &str, clock: &impl Clock) -> Post<Unmoderated> {
+        let now = clock.now();
+        self.body.push_revision(content, now);
+        self.record(actor, None, now)
+    }
+
+    pub fn delete(self, actor: &str, clock: &impl Clock) -> Post<Deleted> {
+        self.record(actor, None, clock.now())
+    }
 }
 
 impl Post<Deleted> {
     pub fn content(&self) -> &str {
-        &self.content
+        self.body.content()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use fixture::clock;
 
     #[test]
     fn test_workflow_publish_allow_delete() {
-        let post = Post::new("Hello Rust!");
-        let unmoderated = post.publish();
-        let published = unmoderated.allow();
+        let clock = clock();
+        let post = Post::new("Hello Rust!", &clock);
+        let unmoderated = post.publish("author", &clock);
+        let published = unmoderated
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
         assert_eq!(published.content(), "Hello Rust!");
-        let deleted = published.delete();
+        let deleted = published.delete("author", &clock);
         assert_eq!(deleted.content(), "Hello Rust!");
     }
 
     #[test]
     fn test_workflow_deny() {
-        let post = Post::new("Spam");
-        let unmoderated = post.publish();
-        let deleted = unmoderated.deny();
+        let clock = clock();
+        let post = Post::new("Spam", &clock);
+        let unmoderated = post.publish("spammer", &clock);
+        let deleted = unmoderated.deny("moderator", "advertising", &clock);
         assert_eq!(deleted.content(), "Spam");
+
+        let denial = deleted.history().last().unwrap();
+        assert_eq!(denial.actor, "moderator");
+        assert_eq!(denial.reason.as_deref(), Some("advertising"));
+    }
+
+    #[test]
+    fn test_history_records_every_transition() {
+        let clock = clock();
+        let post = Post::new("Audited", &clock).publish("author", &clock);
+        assert_eq!(post.history().len(), 1);
+
+        let deleted = post
+            .allow("alice", &ReviewPolicy::default(), &clock)
+            .unwrap()
+            .delete("bob", &clock);
+        let history = deleted.history();
+        assert_eq!(history.len(), 3);
+
+        let steps: Vec<_> = history
+            .iter()
+            .map(|t| (t.from, t.to, t.actor.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (StateKind::New, StateKind::Unmoderated, "author"),
+                (StateKind::Unmoderated, StateKind::Published, "alice"),
+                (StateKind::Published, StateKind::Deleted, "bob"),
+            ]
+        );
+        assert!(history.windows(2).all(|w| w[0].at <= w[1].at));
+    }
+
+    #[test]
+    fn test_quorum_of_one_publishes_immediately() {
+        let clock = clock();
+        let post = Post::new("Solo", &clock).publish("author", &clock);
+        let Approval::Published(published) = post.approve("alice", &ReviewPolicy::new(1), &clock)
+        else {
+            panic!("a quorum of one should publish");
+        };
+        assert_eq!(published.content(), "Solo");
+    }
+
+    #[test]
+    fn test_quorum_requires_distinct_reviewers() {
+        let clock = clock();
+        let post = Post::new("Needs two", &clock).publish("author", &clock);
+        let Approval::Pending(pending) = post.approve("alice", &ReviewPolicy::new(2), &clock)
+        else {
+            panic!("one approval is not enough");
+        };
+        assert_eq!(pending.review().approvals, vec!["alice"]);
+
+        let Approval::Pending(pending) = pending.approve("alice", &clock) else {
+            panic!("the same reviewer must not count twice");
+        };
+        assert_eq!(pending.review().approvals.len(), 1);
+
+        let Approval::Published(published) = pending.approve("bob", &clock) else {
+            panic!("two reviewers should reach quorum");
+        };
+        let approvers: Vec<_> = published
+            .history()
+            .iter()
+            .filter(|t| t.from != StateKind::New)
+            .map(|t| t.actor.as_str())
+            .collect();
+        assert_eq!(approvers, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_allow_is_refused_when_a_quorum_is_configured() {
+        let clock = clock();
+        let post = Post::new("Needs two", &clock).publish("author", &clock);
+        let post = post
+            .allow("alice", &ReviewPolicy::new(2), &clock)
+            .expect_err("one moderator must not skip the quorum");
+        assert_eq!(post.history().len(), 1);
+    }
+
+    #[test]
+    fn test_partially_approved_post_shows_its_content() {
+        let clock = clock();
+        let post = Post::new("Under review", &clock).publish("author", &clock);
+        let Approval::Pending(pending) = post.approve("alice", &ReviewPolicy::new(2), &clock)
+        else {
+            panic!("one approval is not enough");
+        };
+        assert_eq!(pending.content(), "Under review");
+        assert_eq!(pending.live_content(), None);
+    }
+
+    #[test]
+    fn test_partially_approved_post_can_be_denied() {
+        let clock = clock();
+        let post = Post::new("Borderline", &clock).publish("author", &clock);
+        let Approval::Pending(pending) = post.approve("alice", &ReviewPolicy::new(3), &clock)
+        else {
+            panic!("one approval is not enough");
+        };
+        let deleted = pending.deny("bob", "off-topic", &clock);
+        let denial = deleted.history().last().unwrap();
+        assert_eq!(denial.from, StateKind::PartiallyApproved);
+        assert_eq!(denial.reason.as_deref(), Some("off-topic"));
+    }
+
+    #[test]
+    fn test_edit_keeps_live_revision_visible() {
+        let clock = clock();
+        let published = Post::new("First draft", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        let pending = published.edit("Second draft", "author", &clock);
+
+        assert_eq!(pending.content(), "Second draft");
+        assert_eq!(pending.live_content(), Some("First draft"));
+        assert_eq!(pending.revisions().len(), 2);
+
+        let published = pending
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        assert_eq!(published.content(), "Second draft");
+
+        let numbers: Vec<_> = published.revisions().iter().map(|r| r.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_rejected_edit_restores_live_revision() {
+        let clock = clock();
+        let published = Post::new("Original", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        let pending = published.edit("Vandalised", "troll", &clock);
+
+        let published = pending
+            .reject_edit("moderator", "vandalism", &clock)
+            .unwrap();
+        assert_eq!(published.content(), "Original");
+        let rejected: Vec<_> = published.revisions().iter().map(|r| r.rejected).collect();
+        assert_eq!(rejected, vec![false, true]);
+
+        let first_submission = Post::new("Never live", &clock).publish("author", &clock);
+        assert!(first_submission
+            .reject_edit("moderator", "nothing to revert", &clock)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejected_revision_keeps_its_number() {
+        let clock = clock();
+        let published = Post::new("Original", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap()
+            .edit("Vandalised", "troll", &clock)
+            .reject_edit("moderator", "vandalism", &clock)
+            .unwrap();
+        let pending = published.edit("Improved", "author", &clock);
+        assert_eq!(pending.content(), "Improved");
+
+        let numbers: Vec<_> = pending.revisions().iter().map(|r| r.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert!(pending
+            .diff(1, 2)
+            .unwrap()
+            .contains(&DiffLine::Added("Vandalised".to_string())));
+
+        let deleted = pending
+            .reject_edit("moderator", "still not right", &clock)
+            .unwrap()
+            .delete("author", &clock);
+        assert_eq!(deleted.content(), "Original");
+    }
+
+    #[test]
+    fn test_diff_between_revisions() {
+        let clock = clock();
+        let published = Post::new("Hello\nWorld", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        let pending = published.edit("Hello\nRust", "author", &clock);
+
+        assert_eq!(
+            pending.diff(1, 2).unwrap(),
+            vec![
+                DiffLine::Unchanged("Hello".to_string()),
+                DiffLine::Removed("World".to_string()),
+                DiffLine::Added("Rust".to_string()),
+            ]
+        );
+        assert!(pending.diff(1, 3).is_none());
     }
 }
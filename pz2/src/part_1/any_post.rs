@@ -0,0 +1,346 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{
+    Approval, Clock, Deleted, New, PartiallyApproved, Post, PostBody, Published, RetentionPolicy,
+    ReviewPolicy, Revision, Scheduled, StateKind, Transition, Unmoderated,
+};
+
+#[derive(Debug)]
+pub enum AnyPost {
+    New(Post<New>),
+    Unmoderated(Post<Unmoderated>),
+    PartiallyApproved(Post<PartiallyApproved>),
+    Scheduled(Post<Scheduled>),
+    Published(Post<Published>),
+    Deleted(Post<Deleted>),
+}
+
+#[derive(Debug, Error)]
+#[error("cannot {action} a post in state '{}'", .post.state())]
+pub struct TransitionError {
+    action: &'static str,
+    post: AnyPost,
+}
+
+impl TransitionError {
+    pub fn from(&self) -> StateKind {
+        self.post.state()
+    }
+
+    pub fn action(&self) -> &'static str {
+        self.action
+    }
+
+    pub fn into_post(self) -> AnyPost {
+        self.post
+    }
+}
+
+impl AnyPost {
+    pub fn new(content: &str, clock: &impl Clock) -> Self {
+        AnyPost::New(Post::new(content, clock))
+    }
+
+    pub fn state(&self) -> StateKind {
+        match self {
+            AnyPost::New(_) => StateKind::New,
+            AnyPost::Unmoderated(_) => StateKind::Unmoderated,
+            AnyPost::PartiallyApproved(_) => StateKind::PartiallyApproved,
+            AnyPost::Scheduled(_) => StateKind::Scheduled,
+            AnyPost::Published(_) => StateKind::Published,
+            AnyPost::Deleted(_) => StateKind::Deleted,
+        }
+    }
+
+    pub(super) fn body(&self) -> &PostBody {
+        match self {
+            AnyPost::New(post) => &post.body,
+            AnyPost::Unmoderated(post) => &post.body,
+            AnyPost::PartiallyApproved(post) => &post.body,
+            AnyPost::Scheduled(post) => &post.body,
+            AnyPost::Published(post) => &post.body,
+            AnyPost::Deleted(post) => &post.body,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.body().id
+    }
+
+    pub fn content(&self) -> &str {
+        self.body().content()
+    }
+
+    pub fn revisions(&self) -> &[Revision] {
+        &self.body().revisions
+    }
+
+    pub fn history(&self) -> &[Transition] {
+        &self.body().history
+    }
+
+    fn error(self, action: &'static str) -> TransitionError {
+        TransitionError { action, post: self }
+    }
+
+    pub fn publish(self, actor: &str, clock: &impl Clock) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::New(post) => Ok(post.publish(actor, clock).into()),
+            other => Err(other.error("publish")),
+        }
+    }
+
+    pub fn allow(
+        self,
+        actor: &str,
+        policy: &ReviewPolicy,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Unmoderated(post) => post
+                .allow(actor, policy, clock)
+                .map(AnyPost::from)
+                .map_err(|post| AnyPost::from(post).error("allow")),
+            other => Err(other.error("allow")),
+        }
+    }
+
+    pub fn deny(
+        self,
+        actor: &str,
+        reason: &str,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Unmoderated(post) => Ok(post.deny(actor, reason, clock).into()),
+            AnyPost::PartiallyApproved(post) => Ok(post.deny(actor, reason, clock).into()),
+            AnyPost::Scheduled(post) => Ok(post.deny(actor, reason, clock).into()),
+            other => Err(other.error("deny")),
+        }
+    }
+
+    // `policy` only applies when the review starts; a partially approved post
+    // keeps the quorum it was submitted with.
+    pub fn approve(
+        self,
+        reviewer: &str,
+        policy: &ReviewPolicy,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Unmoderated(post) => Ok(post.approve(reviewer, policy, clock).into()),
+            AnyPost::PartiallyApproved(post) => Ok(post.approve(reviewer, clock).into()),
+            other => Err(other.error("approve")),
+        }
+    }
+
+    pub fn schedule(
+        self,
+        actor: &str,
+        go_live: DateTime<Utc>,
+        expires: Option<DateTime<Utc>>,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Unmoderated(post) => post
+                .schedule(actor, go_live, expires, clock)
+                .map(AnyPost::from)
+                .map_err(|post| AnyPost::from(post).error("schedule")),
+            other => Err(other.error("schedule")),
+        }
+    }
+
+    pub fn unschedule(self, actor: &str, clock: &impl Clock) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Scheduled(post) => Ok(post.unschedule(actor, clock).into()),
+            other => Err(other.error("unschedule")),
+        }
+    }
+
+    // Moves a scheduled post live and a published post to deleted once their
+    // time has come; anything else is returned unchanged.
+    pub fn advance(self, clock: &impl Clock) -> AnyPost {
+        match self {
+            AnyPost::Scheduled(post) => match post.go_live_if_due(clock) {
+                Ok(published) => AnyPost::Published(published).advance(clock),
+                Err(post) => post.into(),
+            },
+            AnyPost::Published(post) => match post.expire_if_due(clock) {
+                Ok(deleted) => deleted.into(),
+                Err(post) => post.into(),
+            },
+            other => other,
+        }
+    }
+
+    pub fn edit(
+        self,
+        content: &str,
+        actor: &str,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Published(post) => Ok(post.edit(content, actor, clock).into()),
+            other => Err(other.error("edit")),
+        }
+    }
+
+    pub fn delete(self, actor: &str, clock: &impl Clock) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Published(post) => Ok(post.delete(actor, clock).into()),
+            other => Err(other.error("delete")),
+        }
+    }
+
+    pub fn restore(
+        self,
+        actor: &str,
+        policy: &RetentionPolicy,
+        clock: &impl Clock,
+    ) -> Result<AnyPost, TransitionError> {
+        match self {
+            AnyPost::Deleted(post) => post
+                .restore(actor, policy, clock)
+                .map(AnyPost::from)
+                .map_err(|post| AnyPost::from(post).error("restore")),
+            other => Err(other.error("restore")),
+        }
+    }
+
+    pub fn into_typed<S>(self) -> Result<Post<S>, AnyPost>
+    where
+        Post<S>: TryFrom<AnyPost, Error = AnyPost>,
+    {
+        Post::<S>::try_from(self)
+    }
+}
+
+macro_rules! impl_conversions {
+    ($($state:ident),*) => {
+        $(
+            impl From<Post<$state>> for AnyPost {
+                fn from(post: Post<$state>) -> Self {
+                    AnyPost::$state(post)
+                }
+            }
+
+            impl TryFrom<AnyPost> for Post<$state> {
+                type Error = AnyPost;
+
+                fn try_from(post: AnyPost) -> Result<Self, Self::Error> {
+                    match post {
+                        AnyPost::$state(post) => Ok(post),
+                        other => Err(other),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_conversions!(
+    New,
+    Unmoderated,
+    PartiallyApproved,
+    Scheduled,
+    Published,
+    Deleted
+);
+
+impl From<Approval> for AnyPost {
+    fn from(approval: Approval) -> Self {
+        match approval {
+            Approval::Pending(post) => post.into(),
+            Approval::Published(post) => post.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+
+    #[test]
+    fn test_any_post_follows_typestate_workflow() {
+        let clock = clock();
+        let post = AnyPost::new("Hello Rust!", &clock);
+        assert_eq!(post.state(), StateKind::New);
+
+        let post = post.publish("author", &clock).unwrap();
+        assert_eq!(post.state(), StateKind::Unmoderated);
+
+        let post = post
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        assert_eq!(post.state(), StateKind::Published);
+
+        let post = post.delete("author", &clock).unwrap();
+        assert_eq!(post.state(), StateKind::Deleted);
+        assert_eq!(post.content(), "Hello Rust!");
+        assert_eq!(post.history().len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_transition_returns_error() {
+        let clock = clock();
+        let post = AnyPost::new("Draft", &clock);
+        let err = post
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap_err();
+        assert_eq!(err.from(), StateKind::New);
+        assert_eq!(err.action(), "allow");
+        assert_eq!(err.to_string(), "cannot allow a post in state 'new'");
+
+        let post = err.into_post();
+        assert_eq!(post.content(), "Draft");
+    }
+
+    #[test]
+    fn test_round_trip_through_typed_post() {
+        let clock = clock();
+        let published = Post::new("Typed", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        let any: AnyPost = published.into();
+        assert_eq!(any.state(), StateKind::Published);
+
+        let typed: Post<Published> = any.into_typed().unwrap();
+        assert_eq!(typed.content(), "Typed");
+        assert_eq!(typed.state(), StateKind::Published);
+    }
+
+    #[test]
+    fn test_wrong_typed_conversion_returns_post_back() {
+        let clock = clock();
+        let any: AnyPost = Post::new("Pending", &clock)
+            .publish("author", &clock)
+            .into();
+        let back = Post::<Published>::try_from(any).unwrap_err();
+        assert_eq!(back.state(), StateKind::Unmoderated);
+        assert_eq!(back.content(), "Pending");
+    }
+
+    #[test]
+    fn test_any_post_quorum_review() {
+        let clock = clock();
+        let post = AnyPost::new("Quorum", &clock)
+            .publish("author", &clock)
+            .unwrap();
+        let post = post
+            .approve("alice", &ReviewPolicy::new(2), &clock)
+            .unwrap();
+        assert_eq!(post.state(), StateKind::PartiallyApproved);
+
+        let post = post.approve("bob", &ReviewPolicy::new(2), &clock).unwrap();
+        assert_eq!(post.state(), StateKind::Published);
+
+        let err = post
+            .approve("carol", &ReviewPolicy::new(2), &clock)
+            .unwrap_err();
+        assert_eq!(err.from(), StateKind::Published);
+    }
+}
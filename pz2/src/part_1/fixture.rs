@@ -0,0 +1,9 @@
+use chrono::{TimeZone, Utc};
+
+use super::ManualClock;
+
+// A clock standing still at a fixed moment, so history timestamps in tests
+// only move when a test advances it.
+pub(super) fn clock() -> ManualClock {
+    ManualClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+}
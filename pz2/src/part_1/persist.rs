@@ -0,0 +1,415 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::marker::PhantomData;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{AnyPost, Post, PostBody, Review, Revision, Schedule, State, StateKind, Transition};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawRecord")]
+pub struct PostRecord {
+    pub state: StateKind,
+    #[serde(flatten)]
+    body: PostBody,
+}
+
+#[derive(Deserialize)]
+struct RawRecord {
+    state: StateKind,
+    #[serde(flatten)]
+    body: PostBody,
+}
+
+// The current revision is repeated as `content` so a record stays readable on
+// its own, and a record with nothing but `content` still loads. Every record
+// must carry its id: two posts can share their content and history, so no id
+// can be derived for a record that lost it.
+#[derive(Serialize)]
+struct PostBodyRef<'a> {
+    id: Uuid,
+    content: &'a str,
+    revisions: &'a [Revision],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    live: Option<usize>,
+    history: &'a [Transition],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    review: Option<&'a Review>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<&'a Schedule>,
+}
+
+#[derive(Deserialize)]
+struct PostBodyOwned {
+    id: Uuid,
+    content: String,
+    #[serde(default)]
+    revisions: Vec<Revision>,
+    #[serde(default)]
+    live: Option<usize>,
+    #[serde(default)]
+    history: Vec<Transition>,
+    #[serde(default)]
+    review: Option<Review>,
+    #[serde(default)]
+    schedule: Option<Schedule>,
+}
+
+impl Serialize for PostBody {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        PostBodyRef {
+            id: self.id,
+            content: self.content(),
+            revisions: &self.revisions,
+            live: self.live,
+            history: &self.history,
+            review: self.review.as_ref(),
+            schedule: self.schedule.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PostBody {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let owned = PostBodyOwned::deserialize(deserializer)?;
+        let mut body = PostBody {
+            id: owned.id,
+            revisions: owned.revisions,
+            live: owned.live,
+            history: owned.history,
+            review: owned.review,
+            schedule: owned.schedule,
+        };
+        // A record from before revisions never said when its content was
+        // written; date it by its first transition, or the epoch without one.
+        if body.revisions.is_empty() {
+            let at = body.history.first().map(|t| t.at).unwrap_or_default();
+            body.push_revision(&owned.content, at);
+        }
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("expected a post in state '{expected}', found a record tagged '{found}'")]
+pub struct StateMismatch {
+    pub expected: StateKind,
+    pub found: StateKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("a post record tagged '{state}' {problem}")]
+pub struct InvalidRecord {
+    pub state: StateKind,
+    pub problem: &'static str,
+}
+
+// A record is checked once when it is read, so a typed post never has to
+// discover a missing review or a dangling live revision later on.
+impl TryFrom<RawRecord> for PostRecord {
+    type Error = InvalidRecord;
+
+    fn try_from(raw: RawRecord) -> Result<Self, Self::Error> {
+        let invalid = |problem| InvalidRecord {
+            state: raw.state,
+            problem,
+        };
+        let body = &raw.body;
+        if raw.state == StateKind::PartiallyApproved && body.review.is_none() {
+            return Err(invalid("has no review"));
+        }
+        if raw.state == StateKind::Scheduled && body.schedule.is_none() {
+            return Err(invalid("has no schedule"));
+        }
+        if body.schedule.as_ref().is_some_and(|s| !s.is_valid()) {
+            return Err(invalid("expires before it goes live"));
+        }
+        if body.revisions.iter().all(|r| r.rejected) {
+            return Err(invalid("has no revision that was not rejected"));
+        }
+        match body.live.map(|number| body.revision(number)) {
+            Some(None) => return Err(invalid("marks a missing revision as live")),
+            Some(Some(revision)) if revision.rejected => {
+                return Err(invalid("marks a rejected revision as live"))
+            }
+            _ => {}
+        }
+        Ok(PostRecord {
+            state: raw.state,
+            body: raw.body,
+        })
+    }
+}
+
+impl<S: State> From<Post<S>> for PostRecord {
+    fn from(post: Post<S>) -> Self {
+        PostRecord {
+            state: S::KIND,
+            body: *post.body,
+        }
+    }
+}
+
+impl<S: State> TryFrom<PostRecord> for Post<S> {
+    type Error = StateMismatch;
+
+    fn try_from(record: PostRecord) -> Result<Self, Self::Error> {
+        if record.state != S::KIND {
+            return Err(StateMismatch {
+                expected: S::KIND,
+                found: record.state,
+            });
+        }
+        Ok(Post::rebuild(record.body))
+    }
+}
+
+impl From<AnyPost> for PostRecord {
+    fn from(post: AnyPost) -> Self {
+        match post {
+            AnyPost::New(post) => post.into(),
+            AnyPost::Unmoderated(post) => post.into(),
+            AnyPost::PartiallyApproved(post) => post.into(),
+            AnyPost::Scheduled(post) => post.into(),
+            AnyPost::Published(post) => post.into(),
+            AnyPost::Deleted(post) => post.into(),
+        }
+    }
+}
+
+impl From<PostRecord> for AnyPost {
+    fn from(record: PostRecord) -> Self {
+        let body = record.body;
+        match record.state {
+            StateKind::New => AnyPost::New(Post::rebuild(body)),
+            StateKind::Unmoderated => AnyPost::Unmoderated(Post::rebuild(body)),
+            StateKind::PartiallyApproved => AnyPost::PartiallyApproved(Post::rebuild(body)),
+            StateKind::Scheduled => AnyPost::Scheduled(Post::rebuild(body)),
+            StateKind::Published => AnyPost::Published(Post::rebuild(body)),
+            StateKind::Deleted => AnyPost::Deleted(Post::rebuild(body)),
+        }
+    }
+}
+
+impl<S> Post<S> {
+    fn rebuild(body: PostBody) -> Self {
+        Post {
+            body: Box::new(body),
+            state: PhantomData,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PostRecordRef<'a> {
+    state: StateKind,
+    #[serde(flatten)]
+    body: &'a PostBody,
+}
+
+impl<S: State> Serialize for Post<S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        PostRecordRef {
+            state: S::KIND,
+            body: &self.body,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, S: State> Deserialize<'de> for Post<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = PostRecord::deserialize(deserializer)?;
+        Post::try_from(record).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for AnyPost {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        PostRecordRef {
+            state: self.state(),
+            body: self.body(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnyPost {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PostRecord::deserialize(deserializer).map(AnyPost::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+    use crate::part_1::{
+        Approval, PartiallyApproved, Published, ReviewPolicy, Scheduled, Unmoderated,
+    };
+
+    // A saved record with a fixed id and the given fields.
+    fn record(fields: &str) -> String {
+        format!(
+            r#"{{ "id": "67e55044-10b1-426f-9247-bb680e5fe0c8", {} }}"#,
+            fields
+        )
+    }
+
+    #[test]
+    fn test_serialize_writes_state_next_to_content() {
+        let clock = clock();
+        let post = Post::new("Hello Rust!", &clock).publish("author", &clock);
+        let json = serde_json::to_value(&post).unwrap();
+        assert_eq!(json["state"], "unmoderated");
+        assert_eq!(json["content"], "Hello Rust!");
+    }
+
+    #[test]
+    fn test_round_trip_typed_post() {
+        let clock = clock();
+        let post = Post::new("Saved mid-moderation", &clock).publish("author", &clock);
+        let json = serde_json::to_string(&post).unwrap();
+        let loaded: Post<Unmoderated> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.id(), post.id());
+        assert_eq!(loaded.history(), post.history());
+
+        let published = loaded
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        assert_eq!(published.content(), "Saved mid-moderation");
+        assert_eq!(published.history().len(), 2);
+    }
+
+    #[test]
+    fn test_refuses_to_load_wrong_state() {
+        let json = record(r#""state": "unmoderated", "content": "Not yet""#);
+        let err = serde_json::from_str::<Post<Published>>(&json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("expected a post in state 'published', found a record tagged 'unmoderated'"));
+
+        let record: PostRecord = serde_json::from_str(&json).unwrap();
+        let mismatch = Post::<Published>::try_from(record).unwrap_err();
+        assert_eq!(
+            mismatch,
+            StateMismatch {
+                expected: StateKind::Published,
+                found: StateKind::Unmoderated,
+            }
+        );
+    }
+
+    #[test]
+    fn test_any_post_loads_whatever_state_was_saved() {
+        let json = record(r#""state": "published", "content": "Live""#);
+        let post: AnyPost = serde_json::from_str(&json).unwrap();
+        assert_eq!(post.state(), StateKind::Published);
+        assert!(post.history().is_empty());
+        assert_eq!(post.content(), "Live");
+
+        let json = serde_json::to_value(&post).unwrap();
+        assert_eq!(json["state"], "published");
+        assert_eq!(json["content"], "Live");
+        assert_eq!(json["revisions"][0]["content"], "Live");
+
+        let toml_output = toml::to_string(&post).unwrap();
+        let back: AnyPost = toml::from_str(&toml_output).unwrap();
+        assert_eq!(back.state(), StateKind::Published);
+    }
+
+    #[test]
+    fn test_round_trip_partially_approved_post() {
+        let clock = clock();
+        let post = Post::new("Two reviewers", &clock).publish("author", &clock);
+        let Approval::Pending(pending) = post.approve("alice", &ReviewPolicy::new(2), &clock)
+        else {
+            panic!("one approval is not enough");
+        };
+        let json = serde_json::to_string(&pending).unwrap();
+        assert!(json.contains(r#""state":"partially_approved""#));
+
+        let loaded: Post<PartiallyApproved> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.review(), pending.review());
+        assert!(matches!(
+            loaded.approve("bob", &clock),
+            Approval::Published(_)
+        ));
+    }
+
+    #[test]
+    fn test_refuses_records_without_an_id() {
+        let json = r#"{ "state": "published", "content": "Old post" }"#;
+        let err = serde_json::from_str::<AnyPost>(json).unwrap_err();
+        assert!(err.to_string().contains("missing field `id`"), "{}", err);
+    }
+
+    #[test]
+    fn test_refuses_records_that_break_state_invariants() {
+        let json = record(r#""state": "partially_approved", "content": "x""#);
+        let err = serde_json::from_str::<Post<PartiallyApproved>>(&json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("a post record tagged 'partially_approved' has no review"));
+        assert!(serde_json::from_str::<AnyPost>(&json).is_err());
+
+        let json = record(r#""state": "scheduled", "content": "x""#);
+        let err = serde_json::from_str::<Post<Scheduled>>(&json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("a post record tagged 'scheduled' has no schedule"));
+
+        let json = record(
+            r#""state": "scheduled",
+            "content": "x",
+            "schedule": { "go_live": "2024-01-02T00:00:00Z", "expires": "2024-01-01T00:00:00Z" }"#,
+        );
+        let err = serde_json::from_str::<AnyPost>(&json).unwrap_err();
+        assert!(err.to_string().contains("expires before it goes live"));
+
+        let json = record(r#""state": "published", "content": "x", "live": 2"#);
+        let err = serde_json::from_str::<AnyPost>(&json).unwrap_err();
+        assert!(err.to_string().contains("marks a missing revision as live"));
+
+        let json = record(
+            r#""state": "published",
+            "content": "x",
+            "live": 1,
+            "revisions": [{ "number": 1, "content": "x", "created_at": "2024-01-01T00:00:00Z", "rejected": true }]"#,
+        );
+        let err = serde_json::from_str::<AnyPost>(&json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("has no revision that was not rejected"));
+
+        let json = record(
+            r#""state": "published",
+            "content": "x",
+            "live": 2,
+            "revisions": [
+                { "number": 1, "content": "x", "created_at": "2024-01-01T00:00:00Z" },
+                { "number": 2, "content": "y", "created_at": "2024-01-02T00:00:00Z", "rejected": true }
+            ]"#,
+        );
+        let err = serde_json::from_str::<AnyPost>(&json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("marks a rejected revision as live"));
+    }
+
+    #[test]
+    fn test_round_trip_keeps_revisions() {
+        let clock = clock();
+        let published = Post::new("v1", &clock)
+            .publish("author", &clock)
+            .allow("moderator", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        let pending = published.edit("v2", "author", &clock);
+
+        let json = serde_json::to_string(&pending).unwrap();
+        let loaded: Post<Unmoderated> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.content(), "v2");
+        assert_eq!(loaded.live_content(), Some("v1"));
+        assert_eq!(loaded.revisions(), pending.revisions());
+    }
+}
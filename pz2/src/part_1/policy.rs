@@ -0,0 +1,271 @@
+use serde::Serialize;
+
+use super::{Clock, Deleted, New, Post, Published, Unmoderated};
+
+const POLICY_ACTOR: &str = "policy";
+
+pub trait ContentPolicy {
+    fn name(&self) -> &str;
+
+    // Returns a message describing the problem when the rule fires.
+    fn check(&self, content: &str) -> Option<String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Flag,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleHit {
+    pub rule: String,
+    pub action: PolicyAction,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PolicyReport {
+    pub hits: Vec<RuleHit>,
+}
+
+impl PolicyReport {
+    pub fn is_clean(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    pub fn is_denied(&self) -> bool {
+        self.hits.iter().any(|hit| hit.action == PolicyAction::Deny)
+    }
+
+    fn deny_reason(&self) -> String {
+        let rules: Vec<&str> = self
+            .hits
+            .iter()
+            .filter(|hit| hit.action == PolicyAction::Deny)
+            .map(|hit| hit.rule.as_str())
+            .collect();
+        format!("policy: {}", rules.join(", "))
+    }
+}
+
+#[derive(Default)]
+pub struct PolicySet {
+    rules: Vec<(Box<dyn ContentPolicy>, PolicyAction)>,
+    auto_allow: bool,
+}
+
+impl PolicySet {
+    pub fn new() -> Self {
+        PolicySet::default()
+    }
+
+    pub fn flag(mut self, rule: impl ContentPolicy + 'static) -> Self {
+        self.rules.push((Box::new(rule), PolicyAction::Flag));
+        self
+    }
+
+    pub fn deny(mut self, rule: impl ContentPolicy + 'static) -> Self {
+        self.rules.push((Box::new(rule), PolicyAction::Deny));
+        self
+    }
+
+    pub fn auto_allow(mut self, enabled: bool) -> Self {
+        self.auto_allow = enabled;
+        self
+    }
+
+    pub fn evaluate(&self, content: &str) -> PolicyReport {
+        let hits = self
+            .rules
+            .iter()
+            .filter_map(|(rule, action)| {
+                rule.check(content).map(|message| RuleHit {
+                    rule: rule.name().to_string(),
+                    action: *action,
+                    message,
+                })
+            })
+            .collect();
+        PolicyReport { hits }
+    }
+}
+
+#[derive(Debug)]
+pub enum PolicyOutcome {
+    Denied(Post<Deleted>),
+    Pending(Post<Unmoderated>),
+    Allowed(Post<Published>),
+}
+
+impl Post<New> {
+    pub fn publish_with(
+        self,
+        actor: &str,
+        policies: &PolicySet,
+        clock: &impl Clock,
+    ) -> (PolicyOutcome, PolicyReport) {
+        let report = policies.evaluate(self.body.content());
+        let post = self.publish(actor, clock);
+        let outcome = if report.is_denied() {
+            PolicyOutcome::Denied(post.deny(POLICY_ACTOR, &report.deny_reason(), clock))
+        } else if report.is_clean() && policies.auto_allow {
+            PolicyOutcome::Allowed(post.go_live(POLICY_ACTOR, clock.now()))
+        } else {
+            PolicyOutcome::Pending(post)
+        };
+        (outcome, report)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MinLength(pub usize);
+
+impl ContentPolicy for MinLength {
+    fn name(&self) -> &str {
+        "min_length"
+    }
+
+    fn check(&self, content: &str) -> Option<String> {
+        let len = content.trim().chars().count();
+        (len < self.0).then(|| format!("content has {} characters, minimum is {}", len, self.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MaxLength(pub usize);
+
+impl ContentPolicy for MaxLength {
+    fn name(&self) -> &str {
+        "max_length"
+    }
+
+    fn check(&self, content: &str) -> Option<String> {
+        let len = content.chars().count();
+        (len > self.0).then(|| format!("content has {} characters, maximum is {}", len, self.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BannedWords(pub Vec<String>);
+
+impl BannedWords {
+    pub fn new(words: &[&str]) -> Self {
+        BannedWords(words.iter().map(|w| w.to_lowercase()).collect())
+    }
+}
+
+impl ContentPolicy for BannedWords {
+    fn name(&self) -> &str {
+        "banned_words"
+    }
+
+    fn check(&self, content: &str) -> Option<String> {
+        let found: Vec<&str> = content
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .filter_map(|word| {
+                let word = word.to_lowercase();
+                self.0
+                    .iter()
+                    .find(|banned| **banned == word)
+                    .map(String::as_str)
+            })
+            .collect();
+        (!found.is_empty()).then(|| format!("banned words: {}", found.join(", ")))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MaxLinks(pub usize);
+
+impl ContentPolicy for MaxLinks {
+    fn name(&self) -> &str {
+        "max_links"
+    }
+
+    fn check(&self, content: &str) -> Option<String> {
+        let links = content
+            .split_whitespace()
+            .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+            .count();
+        (links > self.0).then(|| format!("content has {} links, maximum is {}", links, self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+    use crate::part_1::StateKind;
+
+    fn policies() -> PolicySet {
+        PolicySet::new()
+            .deny(MinLength(1))
+            .deny(BannedWords::new(&["casino"]))
+            .flag(MaxLength(40))
+            .flag(MaxLinks(1))
+    }
+
+    #[test]
+    fn test_empty_post_is_auto_denied() {
+        let clock = clock();
+        let (outcome, report) =
+            Post::new("   ", &clock).publish_with("author", &policies(), &clock);
+        let PolicyOutcome::Denied(deleted) = outcome else {
+            panic!("an empty post must be denied");
+        };
+
+        assert_eq!(report.hits.len(), 1);
+        assert_eq!(report.hits[0].rule, "min_length");
+        let denial = deleted.history().last().unwrap();
+        assert_eq!(denial.actor, "policy");
+        assert_eq!(denial.reason.as_deref(), Some("policy: min_length"));
+    }
+
+    #[test]
+    fn test_flagged_post_goes_to_moderation() {
+        let clock = clock();
+        let content = "see https://a.example and https://b.example";
+        let (outcome, report) =
+            Post::new(content, &clock).publish_with("author", &policies(), &clock);
+
+        assert!(matches!(outcome, PolicyOutcome::Pending(_)));
+        let rules: Vec<_> = report.hits.iter().map(|hit| hit.rule.as_str()).collect();
+        assert_eq!(rules, vec!["max_length", "max_links"]);
+        assert!(!report.is_denied());
+    }
+
+    #[test]
+    fn test_clean_post_is_auto_allowed_when_enabled() {
+        let clock = clock();
+        let (outcome, report) =
+            Post::new("Hello Rust!", &clock).publish_with("author", &policies(), &clock);
+        assert!(report.is_clean());
+        assert!(matches!(outcome, PolicyOutcome::Pending(_)));
+
+        let policies = policies().auto_allow(true);
+        let (outcome, _) =
+            Post::new("Hello Rust!", &clock).publish_with("author", &policies, &clock);
+        let PolicyOutcome::Allowed(published) = outcome else {
+            panic!("a clean post should be auto-allowed");
+        };
+        assert_eq!(published.history().last().unwrap().to, StateKind::Published);
+    }
+
+    #[test]
+    fn test_banned_words_match_whole_words_ignoring_case() {
+        let rule = BannedWords::new(&["casino"]);
+        assert!(rule.check("Best CASINO in town!").is_some());
+        assert!(rule.check("casinos are not listed").is_none());
+    }
+
+    #[test]
+    fn test_report_serializes_rules_that_fired() {
+        let report = policies().evaluate("casino");
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["hits"][0]["rule"], "banned_words");
+        assert_eq!(json["hits"][0]["action"], "deny");
+    }
+}
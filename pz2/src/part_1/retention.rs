@@ -0,0 +1,193 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Clock, Deleted, Post, StateKind, Transition, Unmoderated};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub grace: Duration,
+}
+
+impl RetentionPolicy {
+    pub fn new(grace: Duration) -> Self {
+        RetentionPolicy { grace }
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::new(Duration::days(30))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub id: Uuid,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub purged_at: DateTime<Utc>,
+    pub purged_by: String,
+}
+
+impl Post<Deleted> {
+    pub fn deletion(&self) -> Option<&Transition> {
+        self.body
+            .history
+            .iter()
+            .rev()
+            .find(|t| t.to == StateKind::Deleted)
+    }
+
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deletion().map(|t| t.at)
+    }
+
+    pub fn can_restore(&self, policy: &RetentionPolicy, clock: &impl Clock) -> bool {
+        match self.deleted_at() {
+            Some(at) => clock.now() - at <= policy.grace,
+            None => false,
+        }
+    }
+
+    // A restored post goes back through moderation as a whole, so no earlier
+    // revision stays marked as live and an old schedule cannot expire it again.
+    pub fn restore(
+        mut self,
+        actor: &str,
+        policy: &RetentionPolicy,
+        clock: &impl Clock,
+    ) -> Result<Post<Unmoderated>, Self> {
+        if !self.can_restore(policy, clock) {
+            return Err(self);
+        }
+        self.body.live = None;
+        self.body.schedule = None;
+        Ok(self.record(actor, None, clock.now()))
+    }
+
+    pub fn purge(self, actor: &str, clock: &impl Clock) -> Tombstone {
+        let now = clock.now();
+        let (deleted_at, deleted_by, reason) = match self.deletion() {
+            Some(t) => (t.at, t.actor.clone(), t.reason.clone()),
+            None => (now, actor.to_string(), None),
+        };
+        Tombstone {
+            id: self.body.id,
+            deleted_at,
+            deleted_by,
+            reason,
+            purged_at: now,
+            purged_by: actor.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+    use crate::part_1::ReviewPolicy;
+
+    #[test]
+    fn test_restore_within_grace_window() {
+        let clock = clock();
+        let deleted = Post::new("Oops", &clock)
+            .publish("author", &clock)
+            .allow("mod", &ReviewPolicy::default(), &clock)
+            .unwrap()
+            .delete("author", &clock);
+        clock.advance(Duration::days(29));
+        let restored = deleted
+            .restore("author", &RetentionPolicy::default(), &clock)
+            .unwrap();
+
+        assert_eq!(restored.content(), "Oops");
+        assert_eq!(restored.live_content(), None);
+        let last = restored.history().last().unwrap();
+        assert_eq!(
+            (last.from, last.to),
+            (StateKind::Deleted, StateKind::Unmoderated)
+        );
+        assert_eq!(last.at, clock.now());
+    }
+
+    #[test]
+    fn test_restored_post_has_no_edit_to_reject() {
+        let clock = clock();
+        let deleted = Post::new("Only revision", &clock)
+            .publish("author", &clock)
+            .allow("mod", &ReviewPolicy::default(), &clock)
+            .unwrap()
+            .delete("author", &clock);
+        let restored = deleted
+            .restore("author", &RetentionPolicy::default(), &clock)
+            .unwrap();
+
+        let restored = restored
+            .reject_edit("mod", "nothing to revert", &clock)
+            .unwrap_err();
+        assert_eq!(restored.content(), "Only revision");
+        assert_eq!(restored.revisions().len(), 1);
+    }
+
+    #[test]
+    fn test_restored_post_drops_its_old_schedule() {
+        let clock = clock();
+        let expires = clock.now() + Duration::hours(1);
+        let published = Post::new("Flash sale", &clock)
+            .publish("author", &clock)
+            .schedule("mod", clock.now(), Some(expires), &clock)
+            .unwrap()
+            .go_live_if_due(&clock)
+            .unwrap();
+        clock.advance(Duration::hours(2));
+        let deleted = published.expire_if_due(&clock).unwrap();
+
+        let restored = deleted
+            .restore("author", &RetentionPolicy::default(), &clock)
+            .unwrap();
+        let republished = restored
+            .allow("mod", &ReviewPolicy::default(), &clock)
+            .unwrap();
+        assert_eq!(republished.expires(), None);
+        assert!(republished.expire_if_due(&clock).is_err());
+    }
+
+    #[test]
+    fn test_restore_after_grace_window_is_refused() {
+        let clock = clock();
+        let deleted = Post::new("Too late", &clock)
+            .publish("author", &clock)
+            .deny("mod", "spam", &clock);
+        clock.advance(Duration::days(2));
+
+        let policy = RetentionPolicy::new(Duration::days(1));
+        let deleted = deleted.restore("author", &policy, &clock).unwrap_err();
+        assert_eq!(deleted.content(), "Too late");
+    }
+
+    #[test]
+    fn test_purge_keeps_only_deletion_metadata() {
+        let clock = clock();
+        let deleted = Post::new("Secret", &clock)
+            .publish("author", &clock)
+            .deny("mod", "doxxing", &clock);
+        let id = deleted.id();
+        let deleted_at = deleted.deleted_at().unwrap();
+        clock.advance(Duration::days(30));
+
+        let tombstone = deleted.purge("retention-job", &clock);
+        assert_eq!(tombstone.id, id);
+        assert_eq!(tombstone.deleted_at, deleted_at);
+        assert_eq!(tombstone.deleted_by, "mod");
+        assert_eq!(tombstone.reason.as_deref(), Some("doxxing"));
+        assert_eq!(tombstone.purged_at, clock.now());
+        assert_eq!(tombstone.purged_by, "retention-job");
+
+        let json = serde_json::to_string(&tombstone).unwrap();
+        assert!(!json.contains("Secret"));
+    }
+}
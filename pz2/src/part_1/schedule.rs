@@ -0,0 +1,251 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+use super::{Deleted, Post, Published, Scheduled, Unmoderated};
+
+const SCHEDULER: &str = "scheduler";
+
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Cell<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        ManualClock {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub go_live: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl Schedule {
+    pub(super) fn is_valid(&self) -> bool {
+        self.expires.is_none_or(|expires| expires > self.go_live)
+    }
+}
+
+impl Post<Unmoderated> {
+    // A post that would expire before or as it goes live is refused.
+    pub fn schedule(
+        mut self,
+        actor: &str,
+        go_live: DateTime<Utc>,
+        expires: Option<DateTime<Utc>>,
+        clock: &impl Clock,
+    ) -> Result<Post<Scheduled>, Self> {
+        let schedule = Schedule { go_live, expires };
+        if !schedule.is_valid() {
+            return Err(self);
+        }
+        self.body.schedule = Some(schedule);
+        Ok(self.record(actor, None, clock.now()))
+    }
+}
+
+impl Post<Scheduled> {
+    pub fn schedule(&self) -> &Schedule {
+        self.body
+            .schedule
+            .as_ref()
+            .expect("scheduled post always carries a schedule")
+    }
+
+    pub fn go_live_if_due(self, clock: &impl Clock) -> Result<Post<Published>, Self> {
+        let now = clock.now();
+        if now < self.schedule().go_live {
+            return Err(self);
+        }
+        Ok(self.go_live(SCHEDULER, now))
+    }
+
+    pub fn unschedule(mut self, actor: &str, clock: &impl Clock) -> Post<Unmoderated> {
+        self.body.schedule = None;
+        self.record(actor, None, clock.now())
+    }
+
+    pub fn deny(self, actor: &str, reason: &str, clock: &impl Clock) -> Post<Deleted> {
+        self.record(actor, Some(reason), clock.now())
+    }
+}
+
+impl Post<Published> {
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        self.body.schedule.as_ref().and_then(|s| s.expires)
+    }
+
+    pub fn expire_if_due(self, clock: &impl Clock) -> Result<Post<Deleted>, Self> {
+        let now = clock.now();
+        match self.expires() {
+            Some(expires) if now >= expires => Ok(self.record(SCHEDULER, Some("expired"), now)),
+            _ => Err(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+    use crate::part_1::StateKind;
+
+    #[test]
+    fn test_scheduled_post_goes_live_at_the_given_time() {
+        let clock = clock();
+        let go_live = clock.now() + Duration::hours(1);
+        let scheduled = Post::new("Launch", &clock)
+            .publish("author", &clock)
+            .schedule("moderator", go_live, None, &clock)
+            .unwrap();
+
+        let scheduled = scheduled.go_live_if_due(&clock).unwrap_err();
+        clock.advance(Duration::hours(1));
+        let published = scheduled.go_live_if_due(&clock).unwrap();
+
+        assert_eq!(published.content(), "Launch");
+        let last = published.history().last().unwrap();
+        assert_eq!(last.at, go_live);
+        assert_eq!(last.actor, "scheduler");
+        assert!(published.expire_if_due(&clock).is_err());
+    }
+
+    #[test]
+    fn test_published_post_expires_into_deleted() {
+        let clock = clock();
+        let go_live = clock.now();
+        let expires = go_live + Duration::days(7);
+        let published = Post::new("Flash sale", &clock)
+            .publish("author", &clock)
+            .schedule("moderator", go_live, Some(expires), &clock)
+            .unwrap()
+            .go_live_if_due(&clock)
+            .unwrap();
+        assert_eq!(published.expires(), Some(expires));
+
+        clock.advance(Duration::days(6));
+        let published = published.expire_if_due(&clock).unwrap_err();
+        clock.advance(Duration::days(1));
+        let deleted = published.expire_if_due(&clock).unwrap();
+
+        let last = deleted.history().last().unwrap();
+        assert_eq!(
+            (last.from, last.to),
+            (StateKind::Published, StateKind::Deleted)
+        );
+        assert_eq!(last.reason.as_deref(), Some("expired"));
+        assert_eq!(deleted.deleted_at(), Some(expires));
+    }
+
+    #[test]
+    fn test_expiry_must_come_after_going_live() {
+        let clock = clock();
+        let go_live = clock.now() + Duration::hours(1);
+        let post = Post::new("Blink", &clock).publish("author", &clock);
+
+        let post = post
+            .schedule("moderator", go_live, Some(go_live), &clock)
+            .unwrap_err();
+        let post = post
+            .schedule("moderator", go_live, Some(clock.now()), &clock)
+            .unwrap_err();
+        assert_eq!(post.history().len(), 1);
+        assert!(post
+            .schedule(
+                "moderator",
+                go_live,
+                Some(go_live + Duration::seconds(1)),
+                &clock
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_scheduled_post_can_be_unscheduled_or_denied() {
+        let clock = clock();
+        let go_live = clock.now() + Duration::hours(1);
+        let scheduled = Post::new("Second thoughts", &clock)
+            .publish("author", &clock)
+            .schedule("moderator", go_live, None, &clock)
+            .unwrap();
+
+        let unmoderated = scheduled.unschedule("moderator", &clock);
+        let last = unmoderated.history().last().unwrap();
+        assert_eq!(
+            (last.from, last.to),
+            (StateKind::Scheduled, StateKind::Unmoderated)
+        );
+
+        let scheduled = unmoderated
+            .schedule("moderator", go_live, None, &clock)
+            .unwrap();
+        let deleted = scheduled.deny("moderator", "embargo lifted early", &clock);
+        let last = deleted.history().last().unwrap();
+        assert_eq!(
+            (last.from, last.to),
+            (StateKind::Scheduled, StateKind::Deleted)
+        );
+        assert_eq!(last.reason.as_deref(), Some("embargo lifted early"));
+    }
+
+    #[test]
+    fn test_every_transition_is_stamped_by_the_clock() {
+        let clock = clock();
+        let start = clock.now();
+        let post = Post::new("Timed", &clock).publish("author", &clock);
+        clock.advance(Duration::minutes(5));
+        let scheduled = post
+            .schedule("moderator", start + Duration::hours(1), None, &clock)
+            .unwrap();
+        clock.advance(Duration::hours(1));
+        let published = scheduled.go_live_if_due(&clock).unwrap();
+        clock.advance(Duration::days(1));
+        let pending = published.edit("Timed, revised", "author", &clock);
+
+        let times: Vec<_> = pending.history().iter().map(|t| t.at).collect();
+        assert_eq!(
+            times,
+            vec![
+                start,
+                start + Duration::minutes(5),
+                start + Duration::minutes(65),
+                clock.now(),
+            ]
+        );
+        let created: Vec<_> = pending.revisions().iter().map(|r| r.created_at).collect();
+        assert_eq!(created, vec![start, clock.now()]);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{HashMap, VecDeque};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{AnyPost, Clock, Post, State, StateKind, Unmoderated};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StoreError {
+    #[error("post {0} is not in the store")]
+    NotFound(Uuid),
+
+    #[error("post {id} is '{found}', expected '{expected}'")]
+    WrongState {
+        id: Uuid,
+        expected: StateKind,
+        found: StateKind,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct PostStore {
+    posts: HashMap<Uuid, AnyPost>,
+    // Ids of unmoderated posts in submission order. A post that is taken out
+    // keeps its place, so putting it back unchanged does not send it to the end.
+    queue: VecDeque<Uuid>,
+}
+
+impl PostStore {
+    pub fn new() -> Self {
+        PostStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.posts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.posts.is_empty()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&AnyPost> {
+        self.posts.get(&id)
+    }
+
+    pub fn put(&mut self, post: impl Into<AnyPost>) -> Uuid {
+        let post = post.into();
+        let id = post.id();
+        if post.state() == StateKind::Unmoderated {
+            if !self.queue.contains(&id) {
+                self.queue.push_back(id);
+            }
+        } else {
+            self.queue.retain(|queued| *queued != id);
+        }
+        self.posts.insert(id, post);
+        id
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<AnyPost> {
+        self.queue.retain(|queued| *queued != id);
+        self.posts.remove(&id)
+    }
+
+    pub fn take<S: State>(&mut self, id: Uuid) -> Result<Post<S>, StoreError>
+    where
+        Post<S>: TryFrom<AnyPost, Error = AnyPost>,
+    {
+        let post = self.posts.remove(&id).ok_or(StoreError::NotFound(id))?;
+        Post::<S>::try_from(post).map_err(|post| {
+            let found = post.state();
+            self.posts.insert(id, post);
+            StoreError::WrongState {
+                id,
+                expected: S::KIND,
+                found,
+            }
+        })
+    }
+
+    pub fn moderation_queue(&self) -> impl Iterator<Item = &Post<Unmoderated>> {
+        self.queue.iter().filter_map(|id| match self.posts.get(id) {
+            Some(AnyPost::Unmoderated(post)) => Some(post),
+            _ => None,
+        })
+    }
+
+    pub fn next_unmoderated(&mut self) -> Option<Post<Unmoderated>> {
+        let id = self.moderation_queue().next()?.id();
+        self.take(id).ok()
+    }
+
+    pub fn tick(&mut self, clock: &impl Clock) -> Vec<Uuid> {
+        let due: Vec<Uuid> = self
+            .posts
+            .values()
+            .filter(|post| matches!(post.state(), StateKind::Scheduled | StateKind::Published))
+            .map(AnyPost::id)
+            .collect();
+
+        let mut changed = Vec::new();
+        for id in due {
+            let Some(post) = self.posts.remove(&id) else {
+                continue;
+            };
+            let before = post.state();
+            let post = post.advance(clock);
+            if post.state() != before {
+                changed.push(id);
+            }
+            self.posts.insert(id, post);
+        }
+        changed
+    }
+
+    pub fn in_state(&self, state: StateKind) -> impl Iterator<Item = &AnyPost> {
+        self.posts
+            .values()
+            .filter(move |post| post.state() == state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_1::fixture::clock;
+    use crate::part_1::{Published, ReviewPolicy};
+    use chrono::Duration;
+
+    #[test]
+    fn test_queue_follows_submission_order() {
+        let clock = clock();
+        let mut store = PostStore::new();
+        let first = store.put(Post::new("first", &clock).publish("alice", &clock));
+        store.put(Post::new("draft", &clock));
+        let second = store.put(Post::new("second", &clock).publish("bob", &clock));
+
+        let queued: Vec<_> = store.moderation_queue().map(|post| post.id()).collect();
+        assert_eq!(queued, vec![first, second]);
+        assert_eq!(store.in_state(StateKind::New).count(), 1);
+    }
+
+    #[test]
+    fn test_take_and_put_back_after_transition() {
+        let clock = clock();
+        let mut store = PostStore::new();
+        let first = store.put(Post::new("first", &clock).publish("alice", &clock));
+        let second = store.put(Post::new("second", &clock).publish("bob", &clock));
+
+        let post = store.next_unmoderated().unwrap();
+        assert_eq!(post.id(), first);
+        store.put(
+            post.allow("moderator", &ReviewPolicy::default(), &clock)
+                .unwrap(),
+        );
+
+        let queued: Vec<_> = store.moderation_queue().map(|post| post.id()).collect();
+        assert_eq!(queued, vec![second]);
+        assert_eq!(store.get(first).unwrap().state(), StateKind::Published);
+    }
+
+    #[test]
+    fn test_put_back_unchanged_keeps_queue_position() {
+        let clock = clock();
+        let mut store = PostStore::new();
+        let first = store.put(Post::new("first", &clock).publish("alice", &clock));
+        let second = store.put(Post::new("second", &clock).publish("bob", &clock));
+
+        let post = store.take::<Unmoderated>(first).unwrap();
+        assert_eq!(store.moderation_queue().count(), 1);
+        store.put(post);
+
+        let queued: Vec<_> = store.moderation_queue().map(|post| post.id()).collect();
+        assert_eq!(queued, vec![first, second]);
+    }
+
+    #[test]
+    fn test_take_with_wrong_state_leaves_post_in_store() {
+        let clock = clock();
+        let mut store = PostStore::new();
+        let id = store.put(Post::new("pending", &clock).publish("alice", &clock));
+
+        let err = store.take::<Published>(id).unwrap_err();
+        assert_eq!(
+            err,
+            StoreError::WrongState {
+                id,
+                expected: StateKind::Published,
+                found: StateKind::Unmoderated,
+            }
+        );
+        assert_eq!(store.len(), 1);
+
+        let missing = Uuid::new_v4();
+        assert_eq!(
+            store.take::<Unmoderated>(missing).unwrap_err(),
+            StoreError::NotFound(missing)
+        );
+    }
+
+    #[test]
+    fn test_tick_publishes_and_expires_scheduled_posts() {
+        let clock = clock();
+        let mut store = PostStore::new();
+        let scheduled = Post::new("timed", &clock)
+            .publish("alice", &clock)
+            .schedule(
+                "moderator",
+                clock.now() + Duration::hours(1),
+                Some(clock.now() + Duration::hours(2)),
+                &clock,
+            )
+            .unwrap();
+        let id = store.put(scheduled);
+
+        assert!(store.tick(&clock).is_empty());
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(store.tick(&clock), vec![id]);
+        assert_eq!(store.get(id).unwrap().state(), StateKind::Published);
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(store.tick(&clock), vec![id]);
+        assert_eq!(store.get(id).unwrap().state(), StateKind::Deleted);
+    }
+}
@@ -1,59 +1,271 @@
+use chrono::{DateTime, FixedOffset};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+mod billing;
+mod binary;
+mod builder;
+mod convert;
+mod diff;
+#[cfg(test)]
+mod fixture;
+mod migrate;
+mod money;
+mod ndjson;
+mod payload;
+mod redact;
+mod schema;
+mod settings;
+#[cfg(feature = "mock-shard")]
+mod shard;
+mod time;
+mod validate;
+
+pub use billing::{BillingError, EffectiveTariff, GiftCharge, Invoice, TariffCharge, TariffKind};
+pub use binary::{from_msgpack, to_msgpack, BinaryError};
+pub use builder::{BuildError, RequestBuilder, StreamBuilder};
+pub use convert::{convert, ConvertError, Format};
+pub use diff::{Change, Patch, PatchError};
+pub use migrate::{migrate, payload_version, MigrationError, CURRENT_VERSION};
+pub use money::{Currency, Money, MoneyError};
+pub use ndjson::{BatchSummary, LineError, RequestReader, MAX_LINE_LEN};
+pub use payload::{Failure, Payload};
+pub use redact::{RedactError, Redacted, Redaction, RedactionPolicy};
+pub use schema::{request_schema, request_schema_json};
+pub use settings::{Settings, SettingsError, SettingsFlags, SettingsRepr};
+#[cfg(feature = "mock-shard")]
+pub use shard::{MockShard, ShardBehaviour, ShardClient, ShardError};
+pub use time::{CompactDuration, DurationError};
+pub use validate::{ValidationError, ValidationErrors};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
-enum RequestType {
+pub enum RequestType {
     Success,
     Failure,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct PublicTariff {
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct PublicTariff {
     id: u32,
-    price: u32,
-    duration: String,
+    price: Money,
+    duration: CompactDuration,
     description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct PrivateTariff {
-    client_price: u32,
-    duration: String,
+impl PublicTariff {
+    pub fn new(id: u32, price: Money, duration: Duration, description: &str) -> Self {
+        PublicTariff {
+            id,
+            price,
+            duration: duration.into(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn price(&self) -> Money {
+        self.price
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration.as_duration()
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct PrivateTariff {
+    client_price: Money,
+    duration: CompactDuration,
     description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Stream {
+impl PrivateTariff {
+    pub fn new(client_price: Money, duration: Duration, description: &str) -> Self {
+        PrivateTariff {
+            client_price,
+            duration: duration.into(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn client_price(&self) -> Money {
+        self.client_price
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration.as_duration()
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct Stream {
     user_id: String,
     is_private: bool,
-    settings: u32,
+    settings: Settings,
     shard_url: String,
     public_tariff: PublicTariff,
     private_tariff: PrivateTariff,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Gift {
+impl Stream {
+    pub fn builder() -> StreamBuilder {
+        StreamBuilder::default()
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.is_private
+    }
+
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    pub fn shard_url(&self) -> &str {
+        &self.shard_url
+    }
+
+    pub fn public_tariff(&self) -> &PublicTariff {
+        &self.public_tariff
+    }
+
+    pub fn private_tariff(&self) -> &PrivateTariff {
+        &self.private_tariff
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct Gift {
     id: u32,
-    price: u32,
+    price: Money,
     description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct DebugInfo {
-    duration: String,
-    at: String,
+impl Gift {
+    pub fn new(id: u32, price: Money, description: &str) -> Self {
+        Gift {
+            id,
+            price,
+            description: description.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn price(&self) -> Money {
+        self.price
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DebugInfo {
+    duration: CompactDuration,
+    #[serde(with = "time::rfc3339")]
+    #[schemars(with = "DateTime<FixedOffset>")]
+    at: DateTime<FixedOffset>,
+}
+
+impl DebugInfo {
+    pub fn new(duration: Duration, at: DateTime<FixedOffset>) -> Self {
+        DebugInfo {
+            duration: duration.into(),
+            at,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration.as_duration()
+    }
+
+    pub fn at(&self) -> DateTime<FixedOffset> {
+        self.at
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Request {
-    #[serde(rename = "type")]
-    request_type: RequestType,
-    stream: Stream,
-    gifts: Vec<Gift>,
+    payload: Payload,
     debug: DebugInfo,
 }
 
+impl Request {
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    pub fn new(payload: Payload, debug: DebugInfo) -> Self {
+        Request { payload, debug }
+    }
+
+    pub fn request_type(&self) -> RequestType {
+        self.payload.request_type()
+    }
+
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    pub fn stream(&self) -> Option<&Stream> {
+        match &self.payload {
+            Payload::Success { stream, .. } => Some(stream),
+            Payload::Failure { stream, .. } => stream.as_ref(),
+        }
+    }
+
+    pub fn gifts(&self) -> &[Gift] {
+        match &self.payload {
+            Payload::Success { gifts, .. } | Payload::Failure { gifts, .. } => gifts,
+        }
+    }
+
+    pub fn gift_total(&self) -> Result<Option<Money>, MoneyError> {
+        Money::total(self.gifts().iter().map(|gift| &gift.price))
+    }
+
+    pub fn failure(&self) -> Option<&Failure> {
+        match &self.payload {
+            Payload::Success { .. } => None,
+            Payload::Failure { error, .. } => Some(error),
+        }
+    }
+
+    pub fn debug(&self) -> &DebugInfo {
+        &self.debug
+    }
+
+    pub fn set_settings_repr(&mut self, repr: SettingsRepr) {
+        let stream = match &mut self.payload {
+            Payload::Success { stream, .. } => Some(stream),
+            Payload::Failure { stream, .. } => stream.as_mut(),
+        };
+        if let Some(stream) = stream {
+            stream.settings = stream.settings.with_repr(repr);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,15 +309,18 @@ mod tests {
         "#;
 
         let request: Request = serde_json::from_str(json_data).expect("Failed to parse JSON");
-        
-        assert_eq!(request.request_type, RequestType::Success);
-        assert_eq!(request.stream.settings, 45345);
-        assert_eq!(request.stream.public_tariff.price, 100);
-        assert_eq!(request.stream.private_tariff.client_price, 250);
+
+        let stream = request.stream().expect("success payload has a stream");
+
+        assert_eq!(request.request_type(), RequestType::Success);
+        assert_eq!(stream.settings.bits(), 45345);
+        assert_eq!(stream.public_tariff.price, Money::from(100));
+        assert_eq!(stream.private_tariff.client_price, Money::from(250));
+        assert_eq!(request.gift_total(), Ok(Some(Money::from(5))));
 
         let toml_output = toml::to_string(&request).expect("Failed to serialize to TOML");
         println!("{}", toml_output);
-        
+
         assert!(toml_output.contains("shard_url"));
         assert!(toml_output.contains("test public tariff"));
     }
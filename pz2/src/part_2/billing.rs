@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::{CompactDuration, Money, MoneyError, Request, Stream};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TariffKind {
+    Public,
+    Private,
+}
+
+impl fmt::Display for TariffKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TariffKind::Public => f.write_str("public"),
+            TariffKind::Private => f.write_str("private"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveTariff<'a> {
+    pub kind: TariffKind,
+    pub price: Money,
+    pub period: Duration,
+    pub description: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BillingError {
+    #[error("the request has no stream to bill")]
+    NoStream,
+
+    #[error("the {0} tariff has a zero duration")]
+    ZeroPeriod(TariffKind),
+
+    #[error(transparent)]
+    Money(#[from] MoneyError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TariffCharge {
+    pub kind: TariffKind,
+    pub description: String,
+    pub rate: Money,
+    pub period: CompactDuration,
+    pub watched: CompactDuration,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GiftCharge {
+    pub id: u32,
+    pub description: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invoice {
+    pub user_id: String,
+    pub total: Money,
+    pub tariff: TariffCharge,
+    pub gifts: Vec<GiftCharge>,
+}
+
+impl Stream {
+    pub fn effective_tariff(&self) -> EffectiveTariff<'_> {
+        if self.is_private {
+            EffectiveTariff {
+                kind: TariffKind::Private,
+                price: self.private_tariff.client_price(),
+                period: self.private_tariff.duration(),
+                description: self.private_tariff.description(),
+            }
+        } else {
+            EffectiveTariff {
+                kind: TariffKind::Public,
+                price: self.public_tariff.price(),
+                period: self.public_tariff.duration(),
+                description: self.public_tariff.description(),
+            }
+        }
+    }
+}
+
+impl EffectiveTariff<'_> {
+    // The tariff price covers one period; partial periods are charged
+    // proportionally, rounding half a minor unit up.
+    pub fn charge_for(&self, watched: Duration) -> Result<Money, BillingError> {
+        let period = self.period.as_millis();
+        if period == 0 {
+            return Err(BillingError::ZeroPeriod(self.kind));
+        }
+        let scaled = u128::from(self.price.amount())
+            .checked_mul(watched.as_millis())
+            .ok_or(MoneyError::Overflow)?;
+        let amount =
+            u64::try_from((scaled + period / 2) / period).map_err(|_| MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.price.currency()))
+    }
+}
+
+impl Request {
+    pub fn invoice(&self, watched: Duration) -> Result<Invoice, BillingError> {
+        let stream = self.stream().ok_or(BillingError::NoStream)?;
+        let tariff = stream.effective_tariff();
+
+        let amount = tariff.charge_for(watched)?;
+        let gifts: Vec<GiftCharge> = self
+            .gifts()
+            .iter()
+            .map(|gift| GiftCharge {
+                id: gift.id(),
+                description: gift.description().to_string(),
+                amount: gift.price(),
+            })
+            .collect();
+        let total = gifts
+            .iter()
+            .try_fold(amount, |total, gift| total.checked_add(gift.amount))?;
+
+        Ok(Invoice {
+            user_id: stream.user_id().to_string(),
+            total,
+            tariff: TariffCharge {
+                kind: tariff.kind,
+                description: tariff.description.to_string(),
+                rate: tariff.price,
+                period: tariff.period.into(),
+                watched: watched.into(),
+                amount,
+            },
+            gifts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::{fixture, with};
+    use crate::part_2::Currency;
+
+    #[test]
+    fn test_public_stream_uses_public_tariff() {
+        // 100 per hour for 90 minutes, plus gifts of 2 and 3.
+        let invoice = fixture().invoice(Duration::from_secs(90 * 60)).unwrap();
+        assert_eq!(invoice.tariff.kind, TariffKind::Public);
+        assert_eq!(invoice.tariff.amount, Money::from(150));
+        assert_eq!(invoice.gifts.len(), 2);
+        assert_eq!(invoice.total, Money::from(155));
+    }
+
+    #[test]
+    fn test_private_stream_uses_private_tariff() {
+        // 250 per minute for 20 seconds rounds 83.33 down to 83.
+        let invoice = with(&[("/stream/is_private", true.into())])
+            .invoice(Duration::from_secs(20))
+            .unwrap();
+        assert_eq!(invoice.tariff.kind, TariffKind::Private);
+        assert_eq!(invoice.tariff.description, "test private tariff");
+        assert_eq!(invoice.tariff.amount, Money::from(83));
+        assert_eq!(invoice.total, Money::from(88));
+    }
+
+    #[test]
+    fn test_refuses_to_mix_currencies() {
+        let eur = serde_json::json!({ "amount": 3, "currency": "EUR" });
+        let err = with(&[("/gifts/1/price", eur)])
+            .invoice(Duration::from_secs(60))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BillingError::Money(MoneyError::CurrencyMismatch {
+                expected: Currency::DEFAULT,
+                found: "EUR".parse().unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invoice_serializes_to_json_and_toml() {
+        let invoice = fixture().invoice(Duration::from_secs(3600)).unwrap();
+
+        let json = serde_json::to_value(&invoice).unwrap();
+        assert_eq!(json["tariff"]["kind"], "public");
+        assert_eq!(json["tariff"]["watched"], "1h");
+        assert_eq!(json["total"]["amount"], 105);
+
+        let toml_output = toml::to_string(&invoice).unwrap();
+        assert!(toml_output.contains("[[gifts]]"));
+        assert_eq!(toml::from_str::<Invoice>(&toml_output).unwrap(), invoice);
+    }
+}
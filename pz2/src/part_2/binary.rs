@@ -0,0 +1,108 @@
+use serde_json::Value;
+use thiserror::Error;
+
+use super::payload::{decode, DecodeError};
+use super::{MigrationError, Request};
+
+#[derive(Debug, Error)]
+pub enum BinaryError {
+    #[error("failed to write MessagePack: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+
+    #[error("invalid MessagePack at '{path}': {message}")]
+    Invalid { path: String, message: String },
+
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+}
+
+// Fields are written by name rather than position: several of them are
+// optional on the wire, and money and settings are told apart by shape.
+pub fn to_msgpack(request: &Request) -> Result<Vec<u8>, BinaryError> {
+    Ok(rmp_serde::to_vec_named(request)?)
+}
+
+// Read generically and migrated first, exactly like `Format::parse`, so both
+// encodings accept the same payload versions.
+pub fn from_msgpack(bytes: &[u8]) -> Result<Request, BinaryError> {
+    let payload: Value = rmp_serde::from_slice(bytes).map_err(|err| BinaryError::Invalid {
+        path: ".".to_string(),
+        message: err.to_string(),
+    })?;
+    decode(payload).map_err(|err| match err {
+        DecodeError::Migration(err) => err.into(),
+        DecodeError::Invalid { path, message } => BinaryError::Invalid { path, message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::{fixture, fixture_value};
+    use crate::part_2::{Format, SettingsRepr};
+
+    #[test]
+    fn test_round_trips_like_json() {
+        let request = fixture();
+        let bytes = to_msgpack(&request).unwrap();
+        assert_eq!(from_msgpack(&bytes).unwrap(), request);
+
+        let json = serde_json::to_vec(&request).unwrap();
+        assert!(bytes.len() < json.len());
+    }
+
+    #[test]
+    fn test_round_trips_failures_and_named_settings() {
+        let failure = r#"{
+          "type": "failure",
+          "error": { "code": "shard_unavailable", "message": "n3 is down" },
+          "debug": { "duration": "12ms", "at": "2019-06-28T08:35:46+00:00" }
+        }"#;
+        let request = Format::Json.parse(failure).unwrap();
+        assert_eq!(
+            from_msgpack(&to_msgpack(&request).unwrap()).unwrap(),
+            request
+        );
+
+        let mut request = fixture();
+        request.set_settings_repr(SettingsRepr::Names);
+        let decoded = from_msgpack(&to_msgpack(&request).unwrap()).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(
+            decoded.stream().unwrap().settings().repr(),
+            SettingsRepr::Names
+        );
+    }
+
+    #[test]
+    fn test_older_versions_are_migrated() {
+        let v1: serde_json::Value =
+            serde_json::from_str(include_str!("../../fixtures/versions/v1.json")).unwrap();
+        let bytes = rmp_serde::to_vec_named(&v1).unwrap();
+        assert_eq!(from_msgpack(&bytes).unwrap(), fixture());
+
+        let v3 = rmp_serde::to_vec_named(&serde_json::json!({ "version": 3 })).unwrap();
+        assert!(matches!(
+            from_msgpack(&v3),
+            Err(BinaryError::Migration(MigrationError::Unsupported {
+                found: 3
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_decode_error_points_at_bad_field() {
+        let mut value = fixture_value();
+        value["debug"]["duration"] = "soon".into();
+        let bytes = rmp_serde::to_vec_named(&value).unwrap();
+
+        let err = from_msgpack(&bytes).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid MessagePack at 'debug.duration'"),
+            "{}",
+            err
+        );
+        assert!(from_msgpack(&bytes[..bytes.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use thiserror::Error;
+
+use super::{
+    DebugInfo, Failure, Gift, Payload, PrivateTariff, PublicTariff, Request, RequestType, Settings,
+    Stream,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("missing required field '{0}'")]
+pub struct BuildError(pub &'static str);
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamBuilder {
+    user_id: Option<String>,
+    is_private: bool,
+    settings: Settings,
+    shard_url: Option<String>,
+    public_tariff: Option<PublicTariff>,
+    private_tariff: Option<PrivateTariff>,
+}
+
+impl StreamBuilder {
+    pub fn user_id(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    pub fn is_private(mut self, is_private: bool) -> Self {
+        self.is_private = is_private;
+        self
+    }
+
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn shard_url(mut self, shard_url: &str) -> Self {
+        self.shard_url = Some(shard_url.to_string());
+        self
+    }
+
+    pub fn public_tariff(mut self, tariff: PublicTariff) -> Self {
+        self.public_tariff = Some(tariff);
+        self
+    }
+
+    pub fn private_tariff(mut self, tariff: PrivateTariff) -> Self {
+        self.private_tariff = Some(tariff);
+        self
+    }
+
+    pub fn build(self) -> Result<Stream, BuildError> {
+        Ok(Stream {
+            user_id: self.user_id.ok_or(BuildError("stream.user_id"))?,
+            is_private: self.is_private,
+            settings: self.settings,
+            shard_url: self.shard_url.ok_or(BuildError("stream.shard_url"))?,
+            public_tariff: self
+                .public_tariff
+                .ok_or(BuildError("stream.public_tariff"))?,
+            private_tariff: self
+                .private_tariff
+                .ok_or(BuildError("stream.private_tariff"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestBuilder {
+    request_type: Option<RequestType>,
+    stream: Option<Stream>,
+    gifts: Vec<Gift>,
+    error: Option<Failure>,
+    debug: Option<DebugInfo>,
+}
+
+impl RequestBuilder {
+    pub fn request_type(mut self, request_type: RequestType) -> Self {
+        self.request_type = Some(request_type);
+        self
+    }
+
+    pub fn stream(mut self, stream: Stream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    pub fn gift(mut self, gift: Gift) -> Self {
+        self.gifts.push(gift);
+        self
+    }
+
+    pub fn gifts(mut self, gifts: impl IntoIterator<Item = Gift>) -> Self {
+        self.gifts.extend(gifts);
+        self
+    }
+
+    pub fn failure(mut self, error: Failure) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    pub fn debug(mut self, debug: DebugInfo) -> Self {
+        self.debug = Some(debug);
+        self
+    }
+
+    pub fn build(self) -> Result<Request, BuildError> {
+        let payload = match self.request_type.ok_or(BuildError("type"))? {
+            RequestType::Success => Payload::Success {
+                stream: self.stream.ok_or(BuildError("stream"))?,
+                gifts: self.gifts,
+            },
+            RequestType::Failure => Payload::Failure {
+                error: self.error.ok_or(BuildError("error"))?,
+                stream: self.stream,
+                gifts: self.gifts,
+            },
+        };
+        Ok(Request {
+            payload,
+            debug: self.debug.ok_or(BuildError("debug"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::Money;
+    use chrono::DateTime;
+    use std::time::Duration;
+
+    fn stream() -> Stream {
+        Stream::builder()
+            .user_id("8d234120-0bda-49b2-b7e0-fbd3912f6cbf")
+            .settings(Settings::from_bits_retain(45345))
+            .shard_url("https://n3.example.com/sapi")
+            .public_tariff(PublicTariff::new(
+                1,
+                Money::from(100),
+                Duration::from_secs(3600),
+                "test public tariff",
+            ))
+            .private_tariff(PrivateTariff::new(
+                Money::from(250),
+                Duration::from_secs(60),
+                "test private tariff",
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_build_request_and_inspect_it() {
+        let request = Request::builder()
+            .request_type(RequestType::Success)
+            .stream(stream())
+            .gift(Gift::new(1, Money::from(2), "Gift 1"))
+            .gift(Gift::new(2, Money::from(3), "Gift 2"))
+            .debug(DebugInfo::new(
+                Duration::from_millis(234),
+                DateTime::parse_from_rfc3339("2019-06-28T08:35:46+00:00").unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(request.request_type(), RequestType::Success);
+        let stream = request.stream().unwrap();
+        assert_eq!(stream.public_tariff().price(), Money::from(100));
+        assert_eq!(stream.private_tariff().client_price(), Money::from(250));
+        assert!(!stream.is_private());
+        assert_eq!(request.gifts().len(), 2);
+        assert_eq!(request.debug().duration(), Duration::from_millis(234));
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["type"], "success");
+        assert_eq!(json["stream"]["public_tariff"]["duration"], "1h");
+        assert_eq!(json["debug"]["at"], "2019-06-28T08:35:46+00:00");
+        assert_eq!(json["stream"]["settings"], 45345);
+    }
+
+    #[test]
+    fn test_build_reports_missing_field() {
+        let err = Stream::builder().user_id("user").build().unwrap_err();
+        assert_eq!(err, BuildError("stream.shard_url"));
+
+        let err = Request::builder().stream(stream()).build().unwrap_err();
+        assert_eq!(err.to_string(), "missing required field 'type'");
+
+        let err = Request::builder()
+            .request_type(RequestType::Failure)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, BuildError("error"));
+    }
+
+    #[test]
+    fn test_build_failure_request() {
+        let request = Request::builder()
+            .request_type(RequestType::Failure)
+            .failure(Failure::new("timeout", "shard did not answer"))
+            .debug(DebugInfo::new(
+                Duration::from_secs(30),
+                DateTime::parse_from_rfc3339("2019-06-28T08:35:46+00:00").unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        assert_eq!(request.request_type(), RequestType::Failure);
+        assert_eq!(request.failure().unwrap().code(), "timeout");
+        assert!(request.stream().is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::payload::{decode, DecodeError};
+use super::{MigrationError, Request};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+#[derive(Debug, Error)]
+pub enum ConvertError {
+    #[error("unknown format '{0}', expected json, toml or yaml")]
+    UnknownFormat(String),
+
+    #[error("could not detect the input format")]
+    Undetected,
+
+    #[error("invalid {format} at '{path}': {message}")]
+    Invalid {
+        format: Format,
+        path: String,
+        message: String,
+    },
+
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+
+    #[error("failed to write JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("failed to write TOML: {0}")]
+    Toml(#[from] toml::ser::Error),
+
+    #[error("failed to write YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Toml, Format::Yaml];
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let ext = path.extension()?.to_str()?;
+        ext.parse().ok()
+    }
+
+    // Detection only looks at syntax, so a request with a bad field is still
+    // detected and `parse` can report where the problem is. JSON is
+    // recognised by its opening brace; otherwise a TOML table or a YAML
+    // mapping.
+    pub fn detect(input: &str) -> Option<Format> {
+        let trimmed = input.trim_start();
+        if trimmed.starts_with('{') {
+            return Some(Format::Json);
+        }
+        if toml::from_str::<toml::Table>(input).is_ok() {
+            return Some(Format::Toml);
+        }
+        if let Ok(serde_yaml::Value::Mapping(_)) = serde_yaml::from_str(input) {
+            return Some(Format::Yaml);
+        }
+        None
+    }
+
+    // Reads the payload generically first so that older versions can be
+    // migrated before the typed parse.
+    pub fn parse(self, input: &str) -> Result<Request, ConvertError> {
+        let payload: serde_json::Value = match self {
+            Format::Json => serde_json::from_str(input).map_err(|err| self.syntax(err)),
+            Format::Toml => toml::from_str(input).map_err(|err| self.syntax(err)),
+            Format::Yaml => serde_yaml::from_str(input).map_err(|err| self.syntax(err)),
+        }?;
+        decode(payload).map_err(|err| match err {
+            DecodeError::Migration(err) => err.into(),
+            DecodeError::Invalid { path, message } => ConvertError::Invalid {
+                format: self,
+                path,
+                message,
+            },
+        })
+    }
+
+    fn syntax<E: fmt::Display>(self, err: E) -> ConvertError {
+        ConvertError::Invalid {
+            format: self,
+            path: ".".to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn render(self, request: &Request) -> Result<String, ConvertError> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(request)? + "\n",
+            Format::Toml => toml::to_string(request)?,
+            Format::Yaml => serde_yaml::to_string(request)?,
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(ConvertError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Json => "json",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+        };
+        f.write_str(name)
+    }
+}
+
+pub fn convert(input: &str, from: Option<Format>, to: Format) -> Result<String, ConvertError> {
+    let from = from
+        .or_else(|| Format::detect(input))
+        .ok_or(ConvertError::Undetected)?;
+    to.render(&from.parse(input)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::part_2::fixture::{fixture, fixture_value};
+
+    #[test]
+    fn test_round_trip_between_every_pair_of_formats() {
+        let original = fixture();
+        for from in Format::ALL {
+            let text = from.render(&original).unwrap();
+            assert_eq!(Format::detect(&text), Some(from));
+            for to in Format::ALL {
+                let converted = convert(&text, None, to).unwrap();
+                assert_eq!(
+                    to.parse(&converted).unwrap(),
+                    original,
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_from_path_and_name() {
+        assert_eq!(Format::from_path(Path::new("req.yml")), Some(Format::Yaml));
+        assert_eq!(Format::from_path(Path::new("req.TOML")), Some(Format::Toml));
+        assert_eq!(Format::from_path(Path::new("req")), None);
+        assert!(matches!(
+            "xml".parse::<Format>(),
+            Err(ConvertError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_points_at_bad_field() {
+        for (format, field) in [
+            (Format::Json, "stream.public_tariff.duration"),
+            (Format::Json, "debug.at"),
+        ] {
+            let mut value = fixture_value();
+            let mut target = &mut value;
+            for key in field.split('.') {
+                target = &mut target[key];
+            }
+            *target = serde_json::Value::from("soon");
+
+            let err = format.parse(&value.to_string()).unwrap_err();
+            let ConvertError::Invalid { path, .. } = &err else {
+                panic!("expected a parse error, got {:?}", err);
+            };
+            assert_eq!(path, field);
+        }
+
+        let yaml = Format::Yaml
+            .render(&fixture())
+            .unwrap()
+            .replace("duration: 234ms", "duration: 234 parsecs");
+        let err = Format::Yaml.parse(&yaml).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid yaml at 'debug.duration'"));
+    }
+
+    #[test]
+    fn test_detected_input_reports_the_bad_field() {
+        for format in [Format::Toml, Format::Yaml] {
+            let text = format
+                .render(&fixture())
+                .unwrap()
+                .replace("234ms", "234 parsecs");
+            assert_eq!(Format::detect(&text), Some(format));
+
+            let err = convert(&text, None, Format::Json).unwrap_err();
+            let ConvertError::Invalid { path, .. } = &err else {
+                panic!("expected a parse error, got {:?}", err);
+            };
+            assert_eq!(path, "debug.duration");
+        }
+    }
+
+    #[test]
+    fn test_undetectable_input_is_an_error() {
+        let err = convert("just some text", None, Format::Json).unwrap_err();
+        assert!(matches!(err, ConvertError::Undetected));
+    }
+}
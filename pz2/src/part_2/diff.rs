@@ -0,0 +1,433 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+use thiserror::Error;
+
+use super::payload::{decode, DecodeError};
+use super::{CompactDuration, Gift, Request};
+
+// One difference between two requests. Paths use the wire field names joined
+// with dots; a gift is addressed by its id, as in `gifts[id=2].price`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Change {
+    Set {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        old: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new: Option<Value>,
+    },
+    AddGift {
+        gift: Gift,
+    },
+    RemoveGift {
+        id: u32,
+    },
+    // The gift ids in their new order, once the other gift changes are in.
+    ReorderGifts {
+        ids: Vec<u32>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Set {
+                path,
+                old: Some(old),
+                new: Some(new),
+            } => write!(f, "~ {}: {} -> {}", path, old, new),
+            Change::Set {
+                path,
+                new: Some(new),
+                ..
+            } => write!(f, "+ {}: {}", path, new),
+            Change::Set {
+                path,
+                old: Some(old),
+                ..
+            } => write!(f, "- {}: {}", path, old),
+            Change::Set { path, .. } => write!(f, "~ {}", path),
+            Change::AddGift { gift } => write!(
+                f,
+                "+ gifts[id={}]: {}",
+                gift.id(),
+                serde_json::to_string(gift).expect("a gift always serializes")
+            ),
+            Change::RemoveGift { id } => write!(f, "- gifts[id={}]", id),
+            Change::ReorderGifts { ids } => {
+                let ids: Vec<String> = ids.iter().map(u32::to_string).collect();
+                write!(f, "~ gifts: order {}", ids.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Patch {
+    pub changes: Vec<Change>,
+}
+
+impl Patch {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("'{path}' no longer has the value the patch expects")]
+    Conflict { path: String },
+
+    #[error("'{0}' does not point into the request")]
+    BadPath(String),
+
+    #[error("gift {0} is already present")]
+    DuplicateGift(u32),
+
+    #[error("gift {0} is not present")]
+    MissingGift(u32),
+
+    #[error("patched request is invalid at '{path}': {message}")]
+    Invalid { path: String, message: String },
+
+    #[error("failed to serialize the request: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+fn gift_id(gift: &Value) -> Option<u32> {
+    gift.get("id")?.as_u64()?.try_into().ok()
+}
+
+fn gifts_by_id(payload: &Value) -> Vec<(u32, &Value)> {
+    payload["gifts"]
+        .as_array()
+        .map(|gifts| {
+            gifts
+                .iter()
+                .filter_map(|gift| Some((gift_id(gift)?, gift)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Every `duration` field is a `CompactDuration`, so "1h" and "60m" are the
+// same value and not a change.
+fn same_duration(path: &str, old: Option<&Value>, new: Option<&Value>) -> bool {
+    let parse = |value: Option<&Value>| value?.as_str()?.parse::<CompactDuration>().ok();
+    path.rsplit('.').next() == Some("duration")
+        && parse(old).is_some_and(|old| Some(old) == parse(new))
+}
+
+fn diff_values(path: &str, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(&path, old.get(key), new.get(key), changes);
+            }
+        }
+        (old, new) if old != new && !same_duration(path, old, new) => changes.push(Change::Set {
+            path: path.to_string(),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+impl Request {
+    pub fn diff(&self, other: &Request) -> Result<Patch, PatchError> {
+        let mut old = serde_json::to_value(self)?;
+        let mut new = serde_json::to_value(other)?;
+        let mut changes = Vec::new();
+
+        let old_gifts = gifts_by_id(&old);
+        let new_gifts = gifts_by_id(&new);
+        for (id, gift) in &old_gifts {
+            let path = format!("gifts[id={}]", id);
+            match new_gifts.iter().find(|(other, _)| other == id) {
+                Some((_, other)) => diff_values(&path, Some(gift), Some(other), &mut changes),
+                None => changes.push(Change::RemoveGift { id: *id }),
+            }
+        }
+        for (id, gift) in &new_gifts {
+            if !old_gifts.iter().any(|(other, _)| other == id) {
+                let gift = serde_json::from_value((*gift).clone())?;
+                changes.push(Change::AddGift { gift });
+            }
+        }
+
+        // Removals keep the remaining order and additions go to the end; only
+        // an order that differs from that needs its own change.
+        let new_order: Vec<u32> = new_gifts.iter().map(|(id, _)| *id).collect();
+        let patched_order: Vec<u32> = old_gifts
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| new_order.contains(id))
+            .chain(
+                new_order
+                    .iter()
+                    .copied()
+                    .filter(|id| !old_gifts.iter().any(|(old, _)| old == id)),
+            )
+            .collect();
+        if patched_order != new_order {
+            changes.push(Change::ReorderGifts { ids: new_order });
+        }
+
+        for payload in [&mut old, &mut new] {
+            if let Some(object) = payload.as_object_mut() {
+                object.remove("gifts");
+            }
+        }
+        let mut fields = Vec::new();
+        diff_values("", Some(&old), Some(&new), &mut fields);
+        fields.append(&mut changes);
+
+        Ok(Patch { changes: fields })
+    }
+
+    // Each `Set` must find the old value it recorded, so a patch made against
+    // a different version of the request is refused rather than merged.
+    pub fn apply(&self, patch: &Patch) -> Result<Request, PatchError> {
+        let mut payload = serde_json::to_value(self)?;
+        if payload.get("gifts").is_none() {
+            payload["gifts"] = Value::Array(Vec::new());
+        }
+
+        for change in &patch.changes {
+            match change {
+                Change::Set { path, old, new } => set(&mut payload, path, old, new)?,
+                Change::AddGift { gift } => {
+                    let gifts = payload["gifts"].as_array_mut().expect("gifts is a list");
+                    if gifts.iter().any(|other| gift_id(other) == Some(gift.id())) {
+                        return Err(PatchError::DuplicateGift(gift.id()));
+                    }
+                    gifts.push(serde_json::to_value(gift)?);
+                }
+                Change::RemoveGift { id } => {
+                    let gifts = payload["gifts"].as_array_mut().expect("gifts is a list");
+                    let index = gifts
+                        .iter()
+                        .position(|gift| gift_id(gift) == Some(*id))
+                        .ok_or(PatchError::MissingGift(*id))?;
+                    gifts.remove(index);
+                }
+                Change::ReorderGifts { ids } => {
+                    let gifts = payload["gifts"].as_array_mut().expect("gifts is a list");
+                    let mut reordered = Vec::with_capacity(gifts.len());
+                    for id in ids {
+                        let index = gifts
+                            .iter()
+                            .position(|gift| gift_id(gift) == Some(*id))
+                            .ok_or(PatchError::MissingGift(*id))?;
+                        reordered.push(gifts.remove(index));
+                    }
+                    if !gifts.is_empty() {
+                        return Err(PatchError::Conflict {
+                            path: "gifts".to_string(),
+                        });
+                    }
+                    *gifts = reordered;
+                }
+            }
+        }
+
+        decode(payload).map_err(|err| match err {
+            DecodeError::Invalid { path, message } => PatchError::Invalid { path, message },
+            other => PatchError::Invalid {
+                path: ".".to_string(),
+                message: other.to_string(),
+            },
+        })
+    }
+}
+
+fn set(
+    payload: &mut Value,
+    path: &str,
+    old: &Option<Value>,
+    new: &Option<Value>,
+) -> Result<(), PatchError> {
+    let bad_path = || PatchError::BadPath(path.to_string());
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let field = segments
+        .pop()
+        .filter(|field| !field.is_empty())
+        .ok_or_else(bad_path)?;
+
+    let mut target = payload;
+    for segment in segments {
+        target = match segment
+            .strip_prefix("gifts[id=")
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            Some(id) => {
+                let id: u32 = id.parse().map_err(|_| bad_path())?;
+                target["gifts"]
+                    .as_array_mut()
+                    .and_then(|gifts| gifts.iter_mut().find(|gift| gift_id(gift) == Some(id)))
+                    .ok_or(PatchError::MissingGift(id))?
+            }
+            None => {
+                let object = target.as_object_mut().ok_or_else(bad_path)?;
+                object
+                    .entry(segment)
+                    .or_insert_with(|| Value::Object(Map::new()))
+            }
+        };
+    }
+
+    let object = target.as_object_mut().ok_or_else(bad_path)?;
+    if object.get(field) != old.as_ref() && !same_duration(path, object.get(field), old.as_ref()) {
+        return Err(PatchError::Conflict {
+            path: path.to_string(),
+        });
+    }
+    match new {
+        Some(new) => object.insert(field.to_string(), new.clone()),
+        None => object.remove(field),
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::{edited, fixture};
+    use crate::part_2::Money;
+
+    #[test]
+    fn test_identical_requests_have_an_empty_diff() {
+        assert!(fixture().diff(&fixture()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_lists_field_paths_and_gifts_by_id() {
+        let old = fixture();
+        let new = edited(|value| {
+            value["stream"]["settings"] = 1.into();
+            value["stream"]["public_tariff"]["price"] = 120.into();
+            value["gifts"][1]["description"] = "Gift two".into();
+            value["gifts"].as_array_mut().unwrap().remove(0);
+            value["gifts"]
+                .as_array_mut()
+                .unwrap()
+                .push(serde_json::json!({ "id": 7, "price": 9, "description": "Gift 7" }));
+        });
+
+        let patch = old.diff(&new).unwrap();
+        assert_eq!(
+            patch.to_string(),
+            "~ stream.public_tariff.price.amount: 100 -> 120\n\
+             ~ stream.settings: 45345 -> 1\n\
+             - gifts[id=1]\n\
+             ~ gifts[id=2].description: \"Gift 2\" -> \"Gift two\"\n\
+             + gifts[id=7]: {\"id\":7,\"price\":{\"amount\":9,\"currency\":\"USD\"},\"description\":\"Gift 7\"}\n"
+        );
+        assert_eq!(old.apply(&patch).unwrap(), new);
+    }
+
+    #[test]
+    fn test_reordered_gifts_are_recorded() {
+        let old = fixture();
+        let swapped = edited(|value| value["gifts"].as_array_mut().unwrap().swap(0, 1));
+        let patch = old.diff(&swapped).unwrap();
+        assert_eq!(
+            patch.changes,
+            vec![Change::ReorderGifts { ids: vec![2, 1] }]
+        );
+        assert_eq!(old.apply(&patch).unwrap().gifts(), swapped.gifts());
+
+        let new = edited(|value| {
+            let gifts = value["gifts"].as_array_mut().unwrap();
+            gifts.swap(0, 1);
+            gifts.push(serde_json::json!({ "id": 7, "price": 9, "description": "Gift 7" }));
+        });
+
+        let patch = old.diff(&new).unwrap();
+        assert_eq!(
+            patch.changes.last(),
+            Some(&Change::ReorderGifts { ids: vec![2, 1, 7] })
+        );
+        assert_eq!(
+            patch.changes.last().unwrap().to_string(),
+            "~ gifts: order 2, 1, 7"
+        );
+
+        let patched = old.apply(&patch).unwrap();
+        assert_eq!(patched, new);
+        let ids: Vec<u32> = patched.gifts().iter().map(Gift::id).collect();
+        assert_eq!(ids, vec![2, 1, 7]);
+    }
+
+    #[test]
+    fn test_durations_are_compared_by_value() {
+        let old = fixture();
+        let new = edited(|value| value["stream"]["public_tariff"]["duration"] = "60m".into());
+        assert!(old.diff(&new).unwrap().is_empty());
+
+        let new = edited(|value| value["stream"]["public_tariff"]["duration"] = "61m".into());
+        assert_eq!(
+            old.diff(&new).unwrap().to_string(),
+            "~ stream.public_tariff.duration: \"1h\" -> \"61m\"\n"
+        );
+    }
+
+    #[test]
+    fn test_patch_round_trips_through_json() {
+        let old = fixture();
+        let new = edited(|value| {
+            value["type"] = "failure".into();
+            value["error"] = serde_json::json!({ "code": "gone", "message": "" });
+        });
+
+        let patch = old.diff(&new).unwrap();
+        let json = serde_json::to_string(&patch).unwrap();
+        assert!(json.contains(r#""op":"set""#));
+
+        let patch: Patch = serde_json::from_str(&json).unwrap();
+        let patched = old.apply(&patch).unwrap();
+        assert_eq!(patched, new);
+        assert_eq!(patched.failure().unwrap().code(), "gone");
+    }
+
+    #[test]
+    fn test_stale_patch_is_refused() {
+        let old = fixture();
+        let new = edited(|value| value["stream"]["public_tariff"]["price"] = 120.into());
+        let patch = old.diff(&new).unwrap();
+
+        let other = edited(|value| value["stream"]["public_tariff"]["price"] = 110.into());
+        let err = other.apply(&patch).unwrap_err();
+        assert!(matches!(err, PatchError::Conflict { .. }));
+        assert_eq!(
+            err.to_string(),
+            "'stream.public_tariff.price.amount' no longer has the value the patch expects"
+        );
+
+        let add = Patch {
+            changes: vec![Change::AddGift {
+                gift: Gift::new(1, Money::from(5), "again"),
+            }],
+        };
+        assert!(matches!(old.apply(&add), Err(PatchError::DuplicateGift(1))));
+    }
+}
@@ -0,0 +1,30 @@
+use serde_json::Value;
+
+use super::{Format, Request};
+
+pub(super) const FIXTURE: &str = include_str!("../../fixtures/request.json");
+
+pub(super) fn fixture() -> Request {
+    Format::Json.parse(FIXTURE).unwrap()
+}
+
+// The fixture as a generic value, for tests that reshape or break it.
+pub(super) fn fixture_value() -> Value {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+// The fixture with `edits` applied to its JSON before it is parsed.
+pub(super) fn edited(edits: impl FnOnce(&mut Value)) -> Request {
+    let mut value = fixture_value();
+    edits(&mut value);
+    Format::Json.parse(&value.to_string()).unwrap()
+}
+
+// Replaces the values at the given JSON pointers.
+pub(super) fn with(edits: &[(&str, Value)]) -> Request {
+    edited(|value| {
+        for (pointer, new) in edits {
+            *value.pointer_mut(pointer).unwrap() = new.clone();
+        }
+    })
+}
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use thiserror::Error;
+
+// Version 1 is the original payload, which had no `version` field. Version 2
+// gave failures an `error` and prices an explicit currency.
+pub const CURRENT_VERSION: u64 = 2;
+
+// `MIGRATIONS[n]` upgrades a version `n + 1` payload to version `n + 2`; each
+// step may assume the payload is an object.
+const MIGRATIONS: [fn(&mut Value); (CURRENT_VERSION - 1) as usize] = [v1_to_v2];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MigrationError {
+    #[error("expected the payload to be an object")]
+    NotAnObject,
+
+    #[error("invalid version {0}, expected a positive integer")]
+    InvalidVersion(Value),
+
+    #[error("unsupported version {found}, the newest known version is {CURRENT_VERSION}")]
+    Unsupported { found: u64 },
+}
+
+pub fn payload_version(payload: &Value) -> Result<u64, MigrationError> {
+    let object = payload.as_object().ok_or(MigrationError::NotAnObject)?;
+    match object.get("version") {
+        None => Ok(1),
+        Some(version) => match version.as_u64() {
+            Some(0) | None => Err(MigrationError::InvalidVersion(version.clone())),
+            Some(found) if found > CURRENT_VERSION => Err(MigrationError::Unsupported { found }),
+            Some(found) => Ok(found),
+        },
+    }
+}
+
+// Upgrades the payload in place, one version at a time, and returns the
+// version it started from.
+pub fn migrate(payload: &mut Value) -> Result<u64, MigrationError> {
+    let from = payload_version(payload)?;
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(from as usize - 1) {
+        step(payload);
+        payload["version"] = Value::from(index as u64 + 2);
+    }
+    Ok(from)
+}
+
+fn v1_to_v2(payload: &mut Value) {
+    if payload["type"] == "failure" && payload.get("error").is_none() {
+        payload["error"] = json!({ "code": "unspecified", "message": "" });
+    }
+
+    let gifts = payload["gifts"].as_array().map_or(0, Vec::len);
+    let prices = [
+        "/stream/public_tariff/price".to_string(),
+        "/stream/private_tariff/client_price".to_string(),
+    ]
+    .into_iter()
+    .chain((0..gifts).map(|index| format!("/gifts/{}/price", index)));
+
+    for pointer in prices {
+        if let Some(price) = payload.pointer_mut(&pointer) {
+            if price.is_number() {
+                *price = json!({ "amount": price.take(), "currency": "USD" });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::{Format, Request};
+
+    // One fixture per payload version, all describing the same request.
+    const FIXTURES: [&str; CURRENT_VERSION as usize] = [
+        include_str!("../../fixtures/versions/v1.json"),
+        include_str!("../../fixtures/versions/v2.json"),
+    ];
+
+    #[test]
+    fn test_every_version_migrates_to_current() {
+        let current: Value = serde_json::from_str(FIXTURES[CURRENT_VERSION as usize - 1]).unwrap();
+        for (index, fixture) in FIXTURES.iter().enumerate() {
+            let mut payload: Value = serde_json::from_str(fixture).unwrap();
+            assert_eq!(migrate(&mut payload), Ok(index as u64 + 1));
+            assert_eq!(payload, current, "v{}", index + 1);
+        }
+    }
+
+    #[test]
+    fn test_current_version_is_what_we_write() {
+        let current = FIXTURES[CURRENT_VERSION as usize - 1];
+        let request = Format::Json.parse(current).unwrap();
+        let written = serde_json::to_value(&request).unwrap();
+        assert_eq!(written, serde_json::from_str::<Value>(current).unwrap());
+
+        for fixture in FIXTURES {
+            assert_eq!(Format::Json.parse(fixture).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn test_legacy_failure_gains_an_error() {
+        let mut payload = json!({ "type": "failure", "debug": {} });
+        migrate(&mut payload).unwrap();
+        assert_eq!(payload["error"]["code"], "unspecified");
+        assert_eq!(payload["version"], 2);
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        assert_eq!(
+            migrate(&mut json!({ "version": 3 })),
+            Err(MigrationError::Unsupported { found: 3 })
+        );
+        assert_eq!(
+            migrate(&mut json!({ "version": "2" })),
+            Err(MigrationError::InvalidVersion(json!("2")))
+        );
+        assert_eq!(migrate(&mut json!([])), Err(MigrationError::NotAnObject));
+
+        let err = serde_json::from_str::<Request>(r#"{ "version": 3 }"#).unwrap_err();
+        assert!(err.to_string().contains("unsupported version 3"));
+    }
+
+    #[test]
+    fn test_plain_deserialize_migrates_too() {
+        let current = Format::Json
+            .parse(FIXTURES[CURRENT_VERSION as usize - 1])
+            .unwrap();
+        for fixture in FIXTURES {
+            assert_eq!(serde_json::from_str::<Request>(fixture).unwrap(), current);
+            let value: Value = serde_json::from_str(fixture).unwrap();
+            let yaml = serde_yaml::to_string(&value).unwrap();
+            assert_eq!(serde_yaml::from_str::<Request>(&yaml).unwrap(), current);
+        }
+    }
+}
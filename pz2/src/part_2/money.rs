@@ -0,0 +1,300 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation, SubschemaValidation};
+use schemars::JsonSchema;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MoneyError {
+    #[error("invalid currency '{0}': expected a three-letter ISO 4217 code such as USD")]
+    InvalidCurrency(String),
+
+    #[error("cannot add {found} to {expected}")]
+    CurrencyMismatch { expected: Currency, found: Currency },
+
+    #[error("amount overflows")]
+    Overflow,
+}
+
+// An ISO 4217 code, stored inline so money stays `Copy`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    // Prices written before currencies existed are in this currency.
+    pub const DEFAULT: Currency = Currency(*b"USD");
+
+    pub fn code(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("currency codes are ASCII")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::DEFAULT
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code.as_bytes() {
+            &[a, b, c] if code.bytes().all(|byte| byte.is_ascii_uppercase()) => {
+                Ok(Currency([a, b, c]))
+            }
+            _ => Err(MoneyError::InvalidCurrency(code.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Currency({})", self.code())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for Currency {
+    fn schema_name() -> String {
+        "Currency".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^[A-Z]{3}$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+// An amount in the currency's minor units (cents for USD).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct MoneyFields {
+    amount: u64,
+    #[serde(default)]
+    currency: Currency,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Money {
+    amount: u64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: u64, currency: Currency) -> Self {
+        Money { amount, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Money::new(0, currency)
+    }
+
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch {
+                expected: self.currency,
+                found: other.currency,
+            });
+        }
+        let amount = self
+            .amount
+            .checked_add(other.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    // Sums in the currency of the first amount; `None` when there is nothing
+    // to add, since an empty list has no currency.
+    pub fn total<'a>(
+        amounts: impl IntoIterator<Item = &'a Money>,
+    ) -> Result<Option<Money>, MoneyError> {
+        let mut total: Option<Money> = None;
+        for money in amounts {
+            total = Some(match total {
+                Some(total) => total.checked_add(*money)?,
+                None => *money,
+            });
+        }
+        Ok(total)
+    }
+}
+
+// Bare integers are minor units in the default currency.
+impl From<u64> for Money {
+    fn from(amount: u64) -> Self {
+        Money::new(amount, Currency::DEFAULT)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyFields {
+            amount: self.amount,
+            currency: self.currency,
+        }
+        .serialize(serializer)
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an amount in minor units or a map with `amount` and `currency`")
+    }
+
+    fn visit_u64<E: de::Error>(self, amount: u64) -> Result<Money, E> {
+        Ok(Money::from(amount))
+    }
+
+    fn visit_i64<E: de::Error>(self, amount: i64) -> Result<Money, E> {
+        u64::try_from(amount)
+            .map(Money::from)
+            .map_err(|_| E::custom(format!("amount must not be negative, got {}", amount)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Money, A::Error> {
+        let fields = MoneyFields::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        Ok(Money::new(fields.amount, fields.currency))
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+impl JsonSchema for Money {
+    fn schema_name() -> String {
+        "Money".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<u64>(),
+                    MoneyFields::json_schema(gen),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(amount: u64) -> Money {
+        Money::new(amount, Currency::DEFAULT)
+    }
+
+    fn eur(amount: u64) -> Money {
+        Money::new(amount, "EUR".parse().unwrap())
+    }
+
+    #[test]
+    fn test_bare_integer_is_default_currency() {
+        let money: Money = serde_json::from_str("250").unwrap();
+        assert_eq!(money, usd(250));
+
+        let money: Money = serde_json::from_str(r#"{ "amount": 250, "currency": "EUR" }"#).unwrap();
+        assert_eq!(money, eur(250));
+        assert_eq!(serde_json::to_value(money).unwrap()["currency"], "EUR");
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(serde_json::from_str::<Money>("-1").is_err());
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": 1, "currency": "eur" }"#).is_err());
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": 1, "cents": 5 }"#).is_err());
+        assert_eq!(
+            "EURO".parse::<Currency>(),
+            Err(MoneyError::InvalidCurrency("EURO".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_total_checks_currency_and_overflow() {
+        assert_eq!(Money::total(&[usd(2), usd(3)]), Ok(Some(usd(5))));
+        assert_eq!(Money::total(&[]), Ok(None));
+        assert_eq!(
+            Money::total(&[usd(2), eur(3)]),
+            Err(MoneyError::CurrencyMismatch {
+                expected: Currency::DEFAULT,
+                found: "EUR".parse().unwrap(),
+            })
+        );
+        assert_eq!(
+            Money::total(&[usd(u64::MAX), usd(1)]),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_round_trips_through_toml() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Priced {
+            price: Money,
+        }
+        let priced: Priced = toml::from_str("price = 100").unwrap();
+        assert_eq!(priced.price, usd(100));
+
+        let text = toml::to_string(&Priced { price: eur(100) }).unwrap();
+        let back: Priced = toml::from_str(&text).unwrap();
+        assert_eq!(back.price, eur(100));
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use thiserror::Error;
+
+use super::{ConvertError, Format, Request, RequestType};
+
+// Only the first few failures are kept with their message so that a batch
+// full of bad lines does not grow the summary without bound.
+const REPORTED_FAILURES: usize = 10;
+
+// Longest line a reader accepts by default; anything longer is skipped so a
+// single runaway line cannot exhaust memory.
+pub const MAX_LINE_LEN: usize = 1 << 20;
+
+#[derive(Debug, Error)]
+pub enum LineError {
+    #[error("line {line}: failed to read: {source}")]
+    Io { line: usize, source: io::Error },
+
+    #[error("line {line}: {source}")]
+    Invalid { line: usize, source: ConvertError },
+
+    #[error("line {line}: longer than {limit} bytes, skipped")]
+    TooLong { line: usize, limit: usize },
+}
+
+impl LineError {
+    pub fn line(&self) -> usize {
+        match self {
+            LineError::Io { line, .. }
+            | LineError::Invalid { line, .. }
+            | LineError::TooLong { line, .. } => *line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub lines: usize,
+    pub requests: usize,
+    pub successes: usize,
+    pub failures: usize,
+    pub invalid: usize,
+    pub first_errors: Vec<(usize, String)>,
+}
+
+impl BatchSummary {
+    pub fn is_clean(&self) -> bool {
+        self.invalid == 0
+    }
+
+    fn record(&mut self, result: &Result<Request, LineError>) {
+        match result {
+            Ok(request) => {
+                self.requests += 1;
+                match request.request_type() {
+                    RequestType::Success => self.successes += 1,
+                    RequestType::Failure => self.failures += 1,
+                }
+            }
+            Err(err) => {
+                self.invalid += 1;
+                if self.first_errors.len() < REPORTED_FAILURES {
+                    self.first_errors.push((err.line(), err.to_string()));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "lines:     {}", self.lines)?;
+        writeln!(
+            f,
+            "requests:  {} ({} success, {} failure)",
+            self.requests, self.successes, self.failures
+        )?;
+        writeln!(f, "invalid:   {}", self.invalid)?;
+        for (_, message) in &self.first_errors {
+            writeln!(f, "  {}", message)?;
+        }
+        if self.invalid > self.first_errors.len() {
+            writeln!(
+                f,
+                "  ... and {} more",
+                self.invalid - self.first_errors.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Reads one JSON request per line, reusing a single line buffer. Blank lines
+// are skipped, and a line that is too long or not UTF-8 is reported without
+// stopping the batch; only an I/O error ends the iteration.
+pub struct RequestReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    max_line_len: usize,
+    line: usize,
+    done: bool,
+    summary: BatchSummary,
+}
+
+impl<R: BufRead> RequestReader<R> {
+    pub fn new(reader: R) -> Self {
+        RequestReader {
+            reader,
+            buffer: Vec::new(),
+            max_line_len: MAX_LINE_LEN,
+            line: 0,
+            done: false,
+            summary: BatchSummary::default(),
+        }
+    }
+
+    pub fn with_max_line_len(self, max_line_len: usize) -> Self {
+        RequestReader {
+            max_line_len,
+            ..self
+        }
+    }
+
+    pub fn summary(&self) -> &BatchSummary {
+        &self.summary
+    }
+
+    pub fn into_summary(self) -> BatchSummary {
+        self.summary
+    }
+
+    fn next_line(&mut self) -> Option<Result<Request, LineError>> {
+        loop {
+            self.buffer.clear();
+            self.line += 1;
+            let limit = self.max_line_len as u64 + 1;
+            match (&mut self.reader)
+                .take(limit)
+                .read_until(b'\n', &mut self.buffer)
+            {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(source) => return Some(Err(self.io_error(source))),
+            }
+            self.summary.lines += 1;
+
+            if self.buffer.len() > self.max_line_len && self.buffer.last() != Some(&b'\n') {
+                if let Err(source) = self.reader.skip_until(b'\n') {
+                    return Some(Err(self.io_error(source)));
+                }
+                return Some(Err(LineError::TooLong {
+                    line: self.line,
+                    limit: self.max_line_len,
+                }));
+            }
+
+            let text = match std::str::from_utf8(&self.buffer) {
+                Ok(text) => text.trim(),
+                Err(err) => {
+                    return Some(Err(LineError::Invalid {
+                        line: self.line,
+                        source: ConvertError::Invalid {
+                            format: Format::Json,
+                            path: ".".to_string(),
+                            message: format!("line is not valid UTF-8: {}", err),
+                        },
+                    }))
+                }
+            };
+            if text.is_empty() {
+                continue;
+            }
+            return Some(
+                Format::Json
+                    .parse(text)
+                    .map_err(|source| LineError::Invalid {
+                        line: self.line,
+                        source,
+                    }),
+            );
+        }
+    }
+
+    fn io_error(&mut self, source: io::Error) -> LineError {
+        self.done = true;
+        LineError::Io {
+            line: self.line,
+            source,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RequestReader<R> {
+    type Item = Result<Request, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_line();
+        match &result {
+            Some(result) => self.summary.record(result),
+            None => self.done = true,
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::fixture_value;
+    use std::io::Cursor;
+
+    const FAILURE: &str = r#"{"type":"failure","error":{"code":"shard_unavailable","message":"n3 is down"},"debug":{"duration":"12ms","at":"2019-06-28T08:35:46+00:00"}}"#;
+
+    fn line() -> String {
+        fixture_value().to_string()
+    }
+
+    #[test]
+    fn test_reads_requests_with_line_numbers() {
+        let input = format!(
+            "{}\n\n{}\n{{\"type\": \"success\"}}\nnot json\n{}\n",
+            line(),
+            FAILURE,
+            line()
+        );
+        let mut reader = RequestReader::new(Cursor::new(input));
+        let results: Vec<_> = reader.by_ref().collect();
+        assert_eq!(results.len(), 5);
+
+        let bad: Vec<_> = results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .map(LineError::line)
+            .collect();
+        assert_eq!(bad, vec![4, 5]);
+        assert!(results[2]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 4: invalid json"));
+
+        let summary = reader.into_summary();
+        assert_eq!(summary.lines, 6);
+        assert_eq!(summary.requests, 3);
+        assert_eq!(summary.successes, 2);
+        assert_eq!(summary.failures, 1);
+        assert_eq!(summary.invalid, 2);
+        assert!(!summary.is_clean());
+    }
+
+    // Produces the same line over and over without ever holding the whole
+    // batch in memory.
+    struct Repeat {
+        line: Vec<u8>,
+        remaining: usize,
+        offset: usize,
+    }
+
+    impl Read for Repeat {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            let chunk = &self.line[self.offset..];
+            let n = chunk.len().min(buf.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            self.offset += n;
+            if self.offset == self.line.len() {
+                self.offset = 0;
+                self.remaining -= 1;
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_streams_a_large_batch() {
+        let source = Repeat {
+            line: format!("{}\n", line()).into_bytes(),
+            remaining: 10_000,
+            offset: 0,
+        };
+        let mut reader = RequestReader::new(io::BufReader::new(source));
+        assert_eq!(reader.by_ref().filter(Result::is_ok).count(), 10_000);
+        assert!(reader.summary().is_clean());
+        assert_eq!(reader.summary().successes, 10_000);
+    }
+
+    #[test]
+    fn test_keeps_only_the_first_errors() {
+        let input = "oops\n".repeat(25);
+        let mut reader = RequestReader::new(Cursor::new(input));
+        reader.by_ref().for_each(drop);
+
+        let summary = reader.summary();
+        assert_eq!(summary.invalid, 25);
+        assert_eq!(summary.first_errors.len(), REPORTED_FAILURES);
+        assert!(summary.to_string().contains("... and 15 more"));
+    }
+
+    #[test]
+    fn test_skips_overlong_and_non_utf8_lines() {
+        let mut input = format!("{}\n", line()).into_bytes();
+        input.extend_from_slice(&[b'x'; 5000]);
+        input.push(b'\n');
+        input.extend_from_slice(b"{\"type\": \"\xff\"}\n");
+        input.extend_from_slice(format!("{}\n", line()).as_bytes());
+
+        let mut reader = RequestReader::new(Cursor::new(input)).with_max_line_len(1024);
+        let results: Vec<_> = reader.by_ref().collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(LineError::TooLong {
+                line: 2,
+                limit: 1024
+            })
+        ));
+        let err = results[2].as_ref().unwrap_err();
+        assert!(matches!(err, LineError::Invalid { line: 3, .. }));
+        assert!(err.to_string().contains("not valid UTF-8"), "{}", err);
+        assert!(results[3].is_ok());
+
+        let summary = reader.into_summary();
+        assert_eq!(summary.lines, 4);
+        assert_eq!(summary.successes, 2);
+        assert_eq!(summary.invalid, 2);
+    }
+
+    #[test]
+    fn test_overlong_line_without_newline_is_not_buffered() {
+        let source = Repeat {
+            line: vec![b'x'; 4096],
+            remaining: 1024,
+            offset: 0,
+        };
+        let mut reader = RequestReader::new(io::BufReader::new(source)).with_max_line_len(1024);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(LineError::TooLong { line: 1, .. }))
+        ));
+        assert!(reader.buffer.capacity() <= 2048);
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,309 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+use super::migrate::{migrate, MigrationError, CURRENT_VERSION};
+use super::{DebugInfo, Gift, Request, RequestType, Stream};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Failure {
+    code: String,
+    message: String,
+}
+
+impl Failure {
+    pub fn new(code: &str, message: &str) -> Self {
+        Failure {
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    // Failure payloads written before `error` existed carry no details.
+    pub fn unspecified() -> Self {
+        Failure::new("unspecified", "")
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+// The `type` field selects the variant. A failure may still carry the stream
+// and gifts it was about, which is how failures looked before `error` existed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Success {
+        stream: Stream,
+        gifts: Vec<Gift>,
+    },
+    Failure {
+        error: Failure,
+        stream: Option<Stream>,
+        gifts: Vec<Gift>,
+    },
+}
+
+impl Payload {
+    pub fn request_type(&self) -> RequestType {
+        match self {
+            Payload::Success { .. } => RequestType::Success,
+            Payload::Failure { .. } => RequestType::Failure,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RequestRef<'a> {
+    version: u64,
+    #[serde(rename = "type")]
+    request_type: RequestType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a Failure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<&'a Stream>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gifts: Option<&'a [Gift]>,
+    debug: &'a DebugInfo,
+}
+
+// The current wire shape. Payloads only reach it through `decode`, after
+// `migrate` has brought them up to date.
+#[derive(Deserialize)]
+struct RequestOwned {
+    #[serde(rename = "type")]
+    request_type: RequestType,
+    #[serde(default)]
+    error: Option<Failure>,
+    #[serde(default)]
+    stream: Option<Stream>,
+    #[serde(default)]
+    gifts: Vec<Gift>,
+    debug: DebugInfo,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "RequestOwned")]
+struct Current(Request);
+
+impl TryFrom<RequestOwned> for Current {
+    type Error = &'static str;
+
+    fn try_from(owned: RequestOwned) -> Result<Self, Self::Error> {
+        let payload = match owned.request_type {
+            RequestType::Success => {
+                if owned.error.is_some() {
+                    return Err("a success payload cannot carry 'error'");
+                }
+                Payload::Success {
+                    stream: owned.stream.ok_or("missing field `stream`")?,
+                    gifts: owned.gifts,
+                }
+            }
+            RequestType::Failure => Payload::Failure {
+                error: owned.error.unwrap_or_else(Failure::unspecified),
+                stream: owned.stream,
+                gifts: owned.gifts,
+            },
+        };
+        Ok(Current(Request {
+            payload,
+            debug: owned.debug,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum DecodeError {
+    Migration(MigrationError),
+    Invalid { path: String, message: String },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Migration(err) => err.fmt(f),
+            DecodeError::Invalid { path, message } if path == "." => f.write_str(message),
+            DecodeError::Invalid { path, message } => write!(f, "at '{}': {}", path, message),
+        }
+    }
+}
+
+// Every way of reading a `Request` goes through here, so older payloads are
+// upgraded the same way whatever format they arrive in.
+pub(super) fn decode(mut payload: Value) -> Result<Request, DecodeError> {
+    migrate(&mut payload).map_err(DecodeError::Migration)?;
+    serde_path_to_error::deserialize::<_, Current>(payload)
+        .map(|current| current.0)
+        .map_err(|err| DecodeError::Invalid {
+            path: err.path().to_string(),
+            message: err.inner().to_string(),
+        })
+}
+
+// Describes the wire shape for the JSON schema; `Request` itself goes through
+// `RequestRef` and `RequestOwned`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RequestSchema {
+    Success {
+        #[serde(default)]
+        #[schemars(schema_with = "version_schema")]
+        version: Option<u64>,
+        stream: Stream,
+        #[serde(default)]
+        gifts: Vec<Gift>,
+        debug: DebugInfo,
+    },
+    Failure {
+        #[serde(default)]
+        #[schemars(schema_with = "version_schema")]
+        version: Option<u64>,
+        #[serde(default)]
+        error: Option<Failure>,
+        #[serde(default)]
+        stream: Option<Stream>,
+        #[serde(default)]
+        gifts: Vec<Gift>,
+        debug: DebugInfo,
+    },
+}
+
+// Derived from `CURRENT_VERSION` so the committed schema follows a bump.
+fn version_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<Option<u64>>().into_object();
+    schema.number().minimum = Some(1.0);
+    schema.number().maximum = Some(CURRENT_VERSION as f64);
+    schema.into()
+}
+
+impl JsonSchema for Request {
+    fn schema_name() -> String {
+        "Request".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        RequestSchema::json_schema(gen)
+    }
+}
+
+impl Serialize for Request {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (error, stream, gifts) = match &self.payload {
+            Payload::Success { stream, gifts } => (None, Some(stream), Some(gifts.as_slice())),
+            Payload::Failure {
+                error,
+                stream,
+                gifts,
+            } => (
+                Some(error),
+                stream.as_ref(),
+                Some(gifts.as_slice()).filter(|gifts| !gifts.is_empty()),
+            ),
+        };
+        RequestRef {
+            version: CURRENT_VERSION,
+            request_type: self.request_type(),
+            error,
+            stream,
+            gifts,
+            debug: &self.debug,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Request {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let payload = Value::deserialize(deserializer)?;
+        decode(payload).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::Format;
+
+    use crate::part_2::fixture::{fixture_value, FIXTURE};
+
+    const FAILURE: &str = r#"
+    {
+      "type": "failure",
+      "error": { "code": "shard_unavailable", "message": "n3 is down" },
+      "debug": { "duration": "12ms", "at": "2019-06-28T08:35:46+00:00" }
+    }
+    "#;
+
+    #[test]
+    fn test_current_success_shape_still_parses() {
+        let request: Request = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(request.request_type(), RequestType::Success);
+        assert!(request.failure().is_none());
+        assert_eq!(request.gifts().len(), 2);
+
+        // Bare prices are read as the default currency and written back with
+        // it spelled out, under the current version; everything else keeps
+        // its original shape.
+        let json = serde_json::to_value(&request).unwrap();
+        let mut original = fixture_value();
+        original["version"] = 2.into();
+        for pointer in [
+            "/stream/public_tariff/price",
+            "/stream/private_tariff/client_price",
+            "/gifts/0/price",
+            "/gifts/1/price",
+        ] {
+            let price = original.pointer_mut(pointer).unwrap();
+            *price = serde_json::json!({ "amount": price.clone(), "currency": "USD" });
+        }
+        assert_eq!(json, original);
+    }
+
+    #[test]
+    fn test_failure_payload_carries_error() {
+        let request: Request = serde_json::from_str(FAILURE).unwrap();
+        let failure = request.failure().unwrap();
+        assert_eq!(failure.code(), "shard_unavailable");
+        assert_eq!(failure.message(), "n3 is down");
+        assert!(request.stream().is_none());
+        assert!(request.gifts().is_empty());
+
+        for format in Format::ALL {
+            let text = format.render(&request).unwrap();
+            assert_eq!(format.parse(&text).unwrap(), request, "{}", format);
+        }
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("stream").is_none());
+        assert!(json.get("gifts").is_none());
+    }
+
+    #[test]
+    fn test_legacy_failure_with_stream_still_parses() {
+        let legacy = FIXTURE.replace(r#""type": "success""#, r#""type": "failure""#);
+        let request: Request = serde_json::from_str(&legacy).unwrap();
+
+        assert_eq!(request.request_type(), RequestType::Failure);
+        assert_eq!(request.failure(), Some(&Failure::unspecified()));
+        assert_eq!(request.stream().unwrap().settings().bits(), 45345);
+        assert_eq!(request.gifts().len(), 2);
+    }
+
+    #[test]
+    fn test_success_without_stream_is_rejected() {
+        let err = serde_json::from_str::<Request>(
+            r#"{ "type": "success", "debug": { "duration": "1ms", "at": "2019-06-28T08:35:46+00:00" } }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing field `stream`"));
+    }
+}
@@ -0,0 +1,323 @@
+use serde::{ser, Serialize, Serializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+use super::{request_schema, Request};
+
+// Every masked value becomes this many `*`, whatever its length.
+const MASK: &str = "********";
+
+// Values at least this long keep their last few characters after the mask so
+// they can still be told apart in a log; shorter ones are masked entirely.
+const MASK_REVEAL_MIN: usize = 12;
+const MASK_KEEP: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum RedactError {
+    #[error("failed to serialize the request: {0}")]
+    Encode(#[from] serde_json::Error),
+
+    #[error("'{0}' does not name a field of the request")]
+    UnknownField(String),
+
+    #[error("'{0}' is not text and cannot be masked")]
+    NotText(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    // Replaces the value with a SHA-256 digest, so equal values stay equal.
+    Hash,
+    // Replaces a text value with a fixed-width mask.
+    Mask,
+    // Leaves the field out entirely.
+    Drop,
+}
+
+// Fields are addressed with wire names joined by dots; a path that runs into
+// a list applies to every element, so `gifts.description` covers all gifts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionPolicy {
+    rules: Vec<(String, Redaction)>,
+    salt: String,
+}
+
+impl RedactionPolicy {
+    pub fn new() -> Self {
+        RedactionPolicy::default()
+    }
+
+    // Identifiers hashed, the shard masked and debug details dropped.
+    pub fn standard() -> Self {
+        RedactionPolicy::new()
+            .field("stream.user_id", Redaction::Hash)
+            .field("stream.shard_url", Redaction::Mask)
+            .field("debug", Redaction::Drop)
+    }
+
+    pub fn field(mut self, path: &str, redaction: Redaction) -> Self {
+        self.rules.push((path.to_string(), redaction));
+        self
+    }
+
+    pub fn with_salt(mut self, salt: &str) -> Self {
+        self.salt = salt.to_string();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // A rule is checked against the request schema rather than a particular
+    // request, so a misspelt path fails even when the field happens to be
+    // absent, and never silently leaves data in the clear.
+    pub fn check(&self) -> Result<(), RedactError> {
+        let schema = schema();
+        for (path, _) in &self.rules {
+            let segments: Vec<&str> = path.split('.').collect();
+            if !in_schema(schema, schema, &segments) {
+                return Err(RedactError::UnknownField(path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, request: &Request) -> Result<Value, RedactError> {
+        self.check()?;
+        let mut payload = serde_json::to_value(request)?;
+        for (path, redaction) in &self.rules {
+            let segments: Vec<&str> = path.split('.').collect();
+            self.redact(&mut payload, path, &segments, *redaction)?;
+        }
+        Ok(payload)
+    }
+
+    fn redact(
+        &self,
+        value: &mut Value,
+        rule: &str,
+        path: &[&str],
+        redaction: Redaction,
+    ) -> Result<(), RedactError> {
+        let Some((field, rest)) = path.split_first() else {
+            return Ok(());
+        };
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    self.redact(item, rule, path, redaction)?;
+                }
+            }
+            Value::Object(object) if rest.is_empty() => match redaction {
+                Redaction::Drop => {
+                    object.remove(*field);
+                }
+                _ => {
+                    if let Some(target) = object.get_mut(*field) {
+                        *target = self.replace(target, rule, redaction)?;
+                    }
+                }
+            },
+            Value::Object(object) => {
+                if let Some(child) = object.get_mut(*field) {
+                    self.redact(child, rule, rest, redaction)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn replace(
+        &self,
+        value: &Value,
+        rule: &str,
+        redaction: Redaction,
+    ) -> Result<Value, RedactError> {
+        Ok(match redaction {
+            Redaction::Hash => {
+                let text = match value {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                let digest = Sha256::new()
+                    .chain_update(&self.salt)
+                    .chain_update(&text)
+                    .finalize();
+                let mut hex = String::from("sha256:");
+                for byte in digest {
+                    write!(hex, "{:02x}", byte).unwrap();
+                }
+                Value::String(hex)
+            }
+            Redaction::Mask => {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| RedactError::NotText(rule.to_string()))?;
+                let chars: Vec<char> = text.chars().collect();
+                let mut masked = MASK.to_string();
+                if chars.len() >= MASK_REVEAL_MIN {
+                    masked.extend(&chars[chars.len() - MASK_KEEP..]);
+                }
+                Value::String(masked)
+            }
+            Redaction::Drop => Value::Null,
+        })
+    }
+}
+
+fn schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA
+        .get_or_init(|| serde_json::to_value(request_schema()).expect("a schema always serializes"))
+}
+
+// Follows `path` through properties, list items, references and the
+// alternatives of a union; it is known if any branch reaches its end.
+fn in_schema(root: &Value, node: &Value, path: &[&str]) -> bool {
+    let Some((field, rest)) = path.split_first() else {
+        return true;
+    };
+    if let Some(name) = node
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/definitions/"))
+    {
+        return root["definitions"]
+            .get(name)
+            .is_some_and(|target| in_schema(root, target, path));
+    }
+    let branches = ["oneOf", "anyOf", "allOf"]
+        .iter()
+        .filter_map(|key| node.get(*key).and_then(Value::as_array))
+        .flatten()
+        .chain(node.get("items"));
+    for branch in branches {
+        if in_schema(root, branch, path) {
+            return true;
+        }
+    }
+    node.get("properties")
+        .and_then(|properties| properties.get(*field))
+        .is_some_and(|property| in_schema(root, property, rest))
+}
+
+// Serializes a request with the policy applied, in whatever format the
+// serializer writes.
+pub struct Redacted<'a> {
+    request: &'a Request,
+    policy: &'a RedactionPolicy,
+}
+
+impl Serialize for Redacted<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.policy
+            .apply(self.request)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl Request {
+    pub fn redacted<'a>(&'a self, policy: &'a RedactionPolicy) -> Redacted<'a> {
+        Redacted {
+            request: self,
+            policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::{edited, fixture, with};
+
+    #[test]
+    fn test_standard_policy_strips_identifiers_and_debug() {
+        let request = fixture();
+        let json = serde_json::to_value(request.redacted(&RedactionPolicy::standard())).unwrap();
+
+        let user_id = json["stream"]["user_id"].as_str().unwrap();
+        assert!(user_id.starts_with("sha256:"));
+        assert_eq!(user_id.len(), "sha256:".len() + 64);
+        assert_eq!(json["stream"]["shard_url"], "********sapi");
+        assert!(json.get("debug").is_none());
+        assert_eq!(
+            json["stream"]["public_tariff"]["description"],
+            "test public tariff"
+        );
+
+        let toml_output = toml::to_string(&request.redacted(&RedactionPolicy::standard())).unwrap();
+        assert!(!toml_output.contains("8d234120"));
+        assert!(!toml_output.contains("n3.example.com"));
+        assert!(!toml_output.contains("2019-06-28"));
+    }
+
+    #[test]
+    fn test_hash_is_stable_and_salted() {
+        let request = fixture();
+        let policy = RedactionPolicy::new().field("stream.user_id", Redaction::Hash);
+        let first = policy.apply(&request).unwrap();
+        assert_eq!(first, policy.apply(&request).unwrap());
+
+        let salted = policy.clone().with_salt("pepper").apply(&request).unwrap();
+        assert_ne!(first["stream"]["user_id"], salted["stream"]["user_id"]);
+    }
+
+    #[test]
+    fn test_paths_reach_into_lists_and_nested_fields() {
+        let policy = RedactionPolicy::new()
+            .field("gifts.description", Redaction::Mask)
+            .field("debug.at", Redaction::Drop)
+            .field("gifts.price.amount", Redaction::Hash);
+        let json = policy.apply(&fixture()).unwrap();
+
+        assert_eq!(json["gifts"][0]["description"], "********");
+        assert_eq!(json["gifts"][1]["description"], "********");
+        assert_eq!(json["debug"]["duration"], "234ms");
+        assert!(json["debug"].get("at").is_none());
+        assert!(json["gifts"][0]["price"]["amount"]
+            .as_str()
+            .unwrap()
+            .starts_with("sha256:"));
+    }
+
+    #[test]
+    fn test_mask_hides_short_values_and_length() {
+        let policy = RedactionPolicy::new().field("stream.user_id", Redaction::Mask);
+        let json = policy.apply(&fixture()).unwrap();
+        assert_eq!(json["stream"]["user_id"], "********6cbf");
+
+        let short = with(&[("/stream/user_id", "abc".into())]);
+        let json = policy.apply(&short).unwrap();
+        assert_eq!(json["stream"]["user_id"], "********");
+    }
+
+    #[test]
+    fn test_unknown_paths_and_masked_numbers_are_refused() {
+        let typo = RedactionPolicy::new().field("stream.userid", Redaction::Hash);
+        assert!(matches!(
+            typo.apply(&fixture()),
+            Err(RedactError::UnknownField(path)) if path == "stream.userid"
+        ));
+        assert!(serde_json::to_value(fixture().redacted(&typo)).is_err());
+
+        // Known fields are fine even when this request does not carry them.
+        let failure = edited(|value| {
+            value["type"] = "failure".into();
+            value.as_object_mut().unwrap().remove("stream");
+        });
+        let json = RedactionPolicy::standard().apply(&failure).unwrap();
+        assert!(json.get("stream").is_none());
+
+        let number = RedactionPolicy::new().field("gifts.price.amount", Redaction::Mask);
+        assert!(matches!(
+            number.apply(&fixture()),
+            Err(RedactError::NotText(path)) if path == "gifts.price.amount"
+        ));
+    }
+}
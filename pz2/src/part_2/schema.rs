@@ -0,0 +1,44 @@
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use super::Request;
+
+pub fn request_schema() -> RootSchema {
+    schema_for!(Request)
+}
+
+pub fn request_schema_json() -> String {
+    let schema = request_schema();
+    serde_json::to_string_pretty(&schema).expect("a schema always serializes") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMITTED: &str = include_str!("../../schema/request.schema.json");
+
+    #[test]
+    fn test_committed_schema_is_up_to_date() {
+        assert!(
+            request_schema_json() == COMMITTED,
+            "schema/request.schema.json is out of date, regenerate it with \
+             `cargo run --bin stream_request -- schema -o schema/request.schema.json`"
+        );
+    }
+
+    #[test]
+    fn test_schema_uses_wire_names() {
+        let schema: serde_json::Value = serde_json::from_str(&request_schema_json()).unwrap();
+        let variants = schema["oneOf"].as_array().unwrap();
+        let tags: Vec<_> = variants
+            .iter()
+            .map(|v| v["properties"]["type"]["enum"][0].as_str().unwrap())
+            .collect();
+        assert_eq!(tags, vec!["success", "failure"]);
+        assert_eq!(
+            schema["definitions"]["DebugInfo"]["properties"]["at"]["format"],
+            "date-time"
+        );
+    }
+}
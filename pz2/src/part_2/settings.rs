@@ -0,0 +1,323 @@
+use bitflags::bitflags;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{
+    ArrayValidation, InstanceType, Schema, SchemaObject, StringValidation, SubschemaValidation,
+};
+use schemars::JsonSchema;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use thiserror::Error;
+
+bitflags! {
+    // No settings bit has a documented meaning yet. Named flags belong here
+    // once the shard API specifies them; until then strict mode treats every
+    // set bit as unknown.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct SettingsFlags: u32 {
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SettingsError {
+    #[error("unknown settings bits {0:#x}")]
+    UnknownBits(u32),
+
+    #[error("unknown setting '{0}'")]
+    UnknownName(String),
+}
+
+// How settings are written out: the raw number the payload has always used,
+// or the list of flag names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SettingsRepr {
+    #[default]
+    Raw,
+    Names,
+}
+
+impl FromStr for SettingsRepr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "raw" => Ok(SettingsRepr::Raw),
+            "names" => Ok(SettingsRepr::Names),
+            _ => Err(format!(
+                "unknown settings representation '{}', expected raw or names",
+                s
+            )),
+        }
+    }
+}
+
+// Unknown bits are kept as they are so a raw number survives a round trip;
+// `from_bits_strict` and `Request::validate_strict` reject them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Settings {
+    flags: SettingsFlags,
+    repr: SettingsRepr,
+}
+
+impl Settings {
+    pub fn new(flags: SettingsFlags) -> Self {
+        Settings {
+            flags,
+            repr: SettingsRepr::Raw,
+        }
+    }
+
+    pub fn from_bits_retain(bits: u32) -> Self {
+        Settings::new(SettingsFlags::from_bits_retain(bits))
+    }
+
+    pub fn from_bits_strict(bits: u32) -> Result<Self, SettingsError> {
+        Settings::from_bits_retain(bits).check_known()
+    }
+
+    pub fn check_known(self) -> Result<Self, SettingsError> {
+        match self.unknown_bits() {
+            0 => Ok(self),
+            unknown => Err(SettingsError::UnknownBits(unknown)),
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.flags.bits()
+    }
+
+    pub fn flags(&self) -> SettingsFlags {
+        self.flags
+    }
+
+    pub fn unknown_bits(&self) -> u32 {
+        self.flags.bits() & !SettingsFlags::all().bits()
+    }
+
+    pub fn contains(&self, flags: SettingsFlags) -> bool {
+        self.flags.contains(flags)
+    }
+
+    // One name per set bit, lowest bit first. A bit without a flag name is
+    // written as `bit_<n>` so the list still round-trips.
+    pub fn names(&self) -> Vec<String> {
+        (0..u32::BITS)
+            .map(|n| 1 << n)
+            .filter(|bit| self.bits() & bit != 0)
+            .map(|bit| {
+                SettingsFlags::all()
+                    .iter_names()
+                    .find(|(_, flag)| flag.bits() == bit)
+                    .map(|(name, _)| name.to_lowercase())
+                    .unwrap_or_else(|| format!("bit_{}", bit.trailing_zeros()))
+            })
+            .collect()
+    }
+
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Self, SettingsError> {
+        let mut flags = SettingsFlags::empty();
+        for name in names {
+            let name = name.as_ref();
+            flags |=
+                name_to_flag(name).ok_or_else(|| SettingsError::UnknownName(name.to_string()))?;
+        }
+        Ok(Settings::new(flags))
+    }
+
+    pub fn repr(&self) -> SettingsRepr {
+        self.repr
+    }
+
+    pub fn with_repr(self, repr: SettingsRepr) -> Self {
+        Settings { repr, ..self }
+    }
+}
+
+fn name_to_flag(name: &str) -> Option<SettingsFlags> {
+    if let Some(n) = name.strip_prefix("bit_") {
+        return n
+            .parse::<u32>()
+            .ok()
+            .filter(|n| *n < u32::BITS && n.to_string() == name["bit_".len()..])
+            .map(|n| SettingsFlags::from_bits_retain(1 << n));
+    }
+    SettingsFlags::from_name(&name.to_uppercase()).filter(|_| name == name.to_lowercase())
+}
+
+// The representation is only a formatting choice, so equality and hashing
+// ignore it.
+impl PartialEq for Settings {
+    fn eq(&self, other: &Self) -> bool {
+        self.flags == other.flags
+    }
+}
+
+impl Eq for Settings {}
+
+impl Hash for Settings {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.flags.hash(state);
+    }
+}
+
+impl From<SettingsFlags> for Settings {
+    fn from(flags: SettingsFlags) -> Self {
+        Settings::new(flags)
+    }
+}
+
+impl Serialize for Settings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.repr {
+            SettingsRepr::Raw => serializer.serialize_u32(self.bits()),
+            SettingsRepr::Names => {
+                let names = self.names();
+                let mut seq = serializer.serialize_seq(Some(names.len()))?;
+                for name in &names {
+                    seq.serialize_element(name)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+struct SettingsVisitor;
+
+impl<'de> Visitor<'de> for SettingsVisitor {
+    type Value = Settings;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a settings bitfield or a list of setting names")
+    }
+
+    fn visit_u64<E: de::Error>(self, bits: u64) -> Result<Settings, E> {
+        u32::try_from(bits)
+            .map(Settings::from_bits_retain)
+            .map_err(|_| E::custom(format!("settings {} do not fit in 32 bits", bits)))
+    }
+
+    fn visit_i64<E: de::Error>(self, bits: i64) -> Result<Settings, E> {
+        match u64::try_from(bits) {
+            Ok(bits) => self.visit_u64(bits),
+            Err(_) => Err(E::custom(format!(
+                "settings must not be negative, got {}",
+                bits
+            ))),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Settings, A::Error> {
+        let mut names = Vec::new();
+        while let Some(name) = seq.next_element::<String>()? {
+            names.push(name);
+        }
+        Settings::from_names(&names)
+            .map(|settings| settings.with_repr(SettingsRepr::Names))
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Settings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SettingsVisitor)
+    }
+}
+
+impl JsonSchema for Settings {
+    fn schema_name() -> String {
+        "Settings".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut names: Vec<String> = SettingsFlags::all()
+            .iter_names()
+            .map(|(name, _)| name.to_lowercase())
+            .collect();
+        names.push("bit_([0-9]|[12][0-9]|3[01])".to_string());
+        let name = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some(format!("^({})$", names.join("|"))),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let list = SchemaObject {
+            instance_type: Some(InstanceType::Array.into()),
+            array: Some(Box::new(ArrayValidation {
+                items: Some(Schema::from(name).into()),
+                unique_items: Some(true),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![gen.subschema_for::<u32>(), list.into()]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_raw_bits_through_a_round_trip() {
+        let settings: Settings = serde_json::from_str("45345").unwrap();
+        assert_eq!(settings.bits(), 45345);
+        assert_eq!(serde_json::to_string(&settings).unwrap(), "45345");
+
+        assert!(serde_json::from_str::<Settings>("-1").is_err());
+        assert!(serde_json::from_str::<Settings>("4294967296").is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_bits_without_a_name() {
+        assert_eq!(Settings::from_bits_strict(0), Ok(Settings::default()));
+        assert_eq!(
+            Settings::from_bits_strict(45345),
+            Err(SettingsError::UnknownBits(45345))
+        );
+        assert_eq!(Settings::from_bits_retain(45345).unknown_bits(), 45345);
+    }
+
+    #[test]
+    fn test_serializes_in_chosen_repr() {
+        let settings = Settings::from_bits_retain(45345);
+        assert_eq!(serde_json::to_string(&settings).unwrap(), "45345");
+
+        let named = settings.with_repr(SettingsRepr::Names);
+        let json = serde_json::to_string(&named).unwrap();
+        assert_eq!(
+            json,
+            r#"["bit_0","bit_5","bit_8","bit_12","bit_13","bit_15"]"#
+        );
+
+        let back: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, settings);
+        assert_eq!(back.repr(), SettingsRepr::Names);
+    }
+
+    #[test]
+    fn test_rejects_unknown_names() {
+        for name in ["teleport", "bit_32", "bit_01", "BIT_0", "bit_"] {
+            let json = format!(r#"["bit_0", "{}"]"#, name);
+            let err = serde_json::from_str::<Settings>(&json).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("unknown setting '{}'", name)),
+                "{}",
+                err
+            );
+        }
+    }
+}
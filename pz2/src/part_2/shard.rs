@@ -0,0 +1,288 @@
+use chrono::Utc;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+use tiny_http::{Header, Method, Response, Server};
+
+use super::{ConvertError, DebugInfo, Failure, Format, Payload, Request};
+
+// What the mock shard answers with. A success echoes the stream and gifts it
+// received; a failure carries them along with the configured error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardBehaviour {
+    failure: Option<Failure>,
+    latency: Duration,
+}
+
+impl ShardBehaviour {
+    pub fn succeed() -> Self {
+        ShardBehaviour {
+            failure: None,
+            latency: Duration::ZERO,
+        }
+    }
+
+    pub fn fail(code: &str, message: &str) -> Self {
+        ShardBehaviour {
+            failure: Some(Failure::new(code, message)),
+            latency: Duration::ZERO,
+        }
+    }
+
+    pub fn with_latency(self, latency: Duration) -> Self {
+        ShardBehaviour { latency, ..self }
+    }
+
+    fn reply(&self, request: Request) -> Request {
+        let (stream, gifts) = match request.payload {
+            Payload::Success { stream, gifts } => (Some(stream), gifts),
+            Payload::Failure { stream, gifts, .. } => (stream, gifts),
+        };
+        let payload = match (&self.failure, stream) {
+            (None, Some(stream)) => Payload::Success { stream, gifts },
+            (None, None) => Payload::Failure {
+                error: Failure::new("missing_stream", "nothing to serve without a stream"),
+                stream: None,
+                gifts,
+            },
+            (Some(error), stream) => Payload::Failure {
+                error: error.clone(),
+                stream,
+                gifts,
+            },
+        };
+        Request::new(
+            payload,
+            DebugInfo::new(self.latency, Utc::now().fixed_offset()),
+        )
+    }
+}
+
+impl Default for ShardBehaviour {
+    fn default() -> Self {
+        ShardBehaviour::succeed()
+    }
+}
+
+// An in-process stand-in for a shard's "sapi" endpoint, listening on a free
+// local port until it is dropped.
+pub struct MockShard {
+    server: Arc<Server>,
+    handle: Option<JoinHandle<()>>,
+    behaviour: Arc<Mutex<ShardBehaviour>>,
+    received: Arc<Mutex<Vec<Request>>>,
+    url: String,
+}
+
+impl MockShard {
+    pub fn start(behaviour: ShardBehaviour) -> io::Result<MockShard> {
+        let server = Server::http("127.0.0.1:0").map_err(io::Error::other)?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| io::Error::other("mock shard is not listening on TCP"))?;
+
+        let server = Arc::new(server);
+        let behaviour = Arc::new(Mutex::new(behaviour));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handle = {
+            let server = Arc::clone(&server);
+            let behaviour = Arc::clone(&behaviour);
+            let received = Arc::clone(&received);
+            thread::spawn(move || serve(&server, &behaviour, &received))
+        };
+
+        Ok(MockShard {
+            server,
+            handle: Some(handle),
+            behaviour,
+            received,
+            url: format!("http://127.0.0.1:{}/sapi", port),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_behaviour(&self, behaviour: ShardBehaviour) {
+        *self.behaviour.lock().unwrap() = behaviour;
+    }
+
+    pub fn received(&self) -> Vec<Request> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockShard {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(server: &Server, behaviour: &Mutex<ShardBehaviour>, received: &Mutex<Vec<Request>>) {
+    for mut incoming in server.incoming_requests() {
+        if *incoming.method() != Method::Post {
+            let _ = incoming.respond(Response::from_string("expected POST").with_status_code(405));
+            continue;
+        }
+
+        let mut body = String::new();
+        let parsed = incoming
+            .as_reader()
+            .read_to_string(&mut body)
+            .map_err(|err| err.to_string())
+            .and_then(|_| Format::Json.parse(&body).map_err(|err| err.to_string()));
+        let request = match parsed {
+            Ok(request) => request,
+            Err(message) => {
+                let _ = incoming.respond(Response::from_string(message).with_status_code(400));
+                continue;
+            }
+        };
+
+        received.lock().unwrap().push(request.clone());
+        let behaviour = behaviour.lock().unwrap().clone();
+        thread::sleep(behaviour.latency);
+
+        let reply = behaviour.reply(request);
+        let response = match serde_json::to_string(&reply) {
+            Ok(body) => {
+                let json = Header::from_bytes("Content-Type", "application/json").unwrap();
+                Response::from_string(body).with_header(json)
+            }
+            Err(err) => Response::from_string(err.to_string()).with_status_code(500),
+        };
+        let _ = incoming.respond(response);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ShardError {
+    #[error("the request has no stream, so there is no shard to send it to")]
+    NoStream,
+
+    #[error("shard answered {status}: {body}")]
+    Status { status: u16, body: String },
+
+    #[error("failed to reach the shard: {0}")]
+    Transport(String),
+
+    #[error("shard sent an invalid response: {0}")]
+    Invalid(#[from] ConvertError),
+
+    #[error("failed to serialize the request: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+pub struct ShardClient {
+    agent: ureq::Agent,
+}
+
+impl ShardClient {
+    pub fn new(timeout: Duration) -> Self {
+        ShardClient {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    // Sends the request to its own stream's shard.
+    pub fn send(&self, request: &Request) -> Result<Request, ShardError> {
+        let stream = request.stream().ok_or(ShardError::NoStream)?;
+        self.post(stream.shard_url(), request)
+    }
+
+    pub fn post(&self, url: &str, request: &Request) -> Result<Request, ShardError> {
+        let body = serde_json::to_string(request)?;
+        let response = self
+            .agent
+            .post(url)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        match response {
+            Ok(response) => {
+                let text = response
+                    .into_string()
+                    .map_err(|err| ShardError::Transport(err.to_string()))?;
+                Ok(Format::Json.parse(&text)?)
+            }
+            Err(ureq::Error::Status(status, response)) => Err(ShardError::Status {
+                status,
+                body: response.into_string().unwrap_or_default(),
+            }),
+            Err(ureq::Error::Transport(err)) => Err(ShardError::Transport(err.to_string())),
+        }
+    }
+}
+
+impl Default for ShardClient {
+    fn default() -> Self {
+        ShardClient::new(Duration::from_secs(5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part_2::fixture::edited;
+    use crate::part_2::RequestType;
+    use std::time::Instant;
+
+    // The fixture's shard, pointed at the mock instead.
+    fn request_to(shard: &MockShard) -> Request {
+        edited(|value| value["stream"]["shard_url"] = shard.url().into())
+    }
+
+    #[test]
+    fn test_success_echoes_stream_and_gifts() {
+        let shard = MockShard::start(ShardBehaviour::succeed()).unwrap();
+        let request = request_to(&shard);
+
+        let reply = ShardClient::default().send(&request).unwrap();
+        assert_eq!(reply.request_type(), RequestType::Success);
+        assert_eq!(reply.stream(), request.stream());
+        assert_eq!(reply.gifts(), request.gifts());
+        assert_eq!(shard.received(), vec![request]);
+    }
+
+    #[test]
+    fn test_failure_and_latency_are_configurable() {
+        let shard = MockShard::start(ShardBehaviour::succeed()).unwrap();
+        let request = request_to(&shard);
+        let latency = Duration::from_millis(50);
+        shard.set_behaviour(ShardBehaviour::fail("overloaded", "try n4").with_latency(latency));
+
+        let started = Instant::now();
+        let reply = ShardClient::default().send(&request).unwrap();
+        assert!(started.elapsed() >= latency);
+
+        assert_eq!(reply.failure(), Some(&Failure::new("overloaded", "try n4")));
+        assert_eq!(reply.stream(), request.stream());
+        assert_eq!(reply.debug().duration(), latency);
+    }
+
+    #[test]
+    fn test_client_reports_timeouts_and_bad_requests() {
+        let shard =
+            MockShard::start(ShardBehaviour::succeed().with_latency(Duration::from_millis(500)))
+                .unwrap();
+        let request = request_to(&shard);
+        let err = ShardClient::new(Duration::from_millis(50))
+            .send(&request)
+            .unwrap_err();
+        assert!(matches!(err, ShardError::Transport(_)), "{:?}", err);
+
+        shard.set_behaviour(ShardBehaviour::succeed());
+        let agent = ureq::agent();
+        let Err(ureq::Error::Status(status, _)) = agent.post(shard.url()).send_string("{}") else {
+            panic!("expected the mock to reject an invalid payload");
+        };
+        assert_eq!(status, 400);
+    }
+}
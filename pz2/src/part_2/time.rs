@@ -0,0 +1,302 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+// Sizes in nanoseconds, largest unit first, so `Display` can pick the most
+// compact spelling. `us` and `ns` keep sub-millisecond durations exact.
+const UNITS: [(&str, u128); 7] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DurationError {
+    #[error("empty duration")]
+    Empty,
+
+    #[error("invalid duration '{0}': expected <number><unit>, e.g. 1h or 234ms")]
+    Malformed(String),
+
+    #[error(
+        "invalid duration '{input}': unknown unit '{unit}', expected d, h, m, s, ms, us or ns"
+    )]
+    UnknownUnit { input: String, unit: String },
+
+    #[error("invalid duration '{0}': value is too large")]
+    Overflow(String),
+}
+
+// A duration written the way the payload spells it: "1h", "1m", "234ms",
+// or several segments such as "1h30m". A parsed value is written back exactly
+// as it was spelled, so "90m" stays "90m"; one built from a `Duration` uses
+// the most compact spelling. Comparisons only look at the length of time.
+#[derive(Debug, Clone, Default)]
+pub struct CompactDuration {
+    duration: Duration,
+    spelling: Option<Box<str>>,
+}
+
+impl CompactDuration {
+    pub fn as_duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl PartialEq for CompactDuration {
+    fn eq(&self, other: &Self) -> bool {
+        self.duration == other.duration
+    }
+}
+
+impl Eq for CompactDuration {}
+
+impl PartialOrd for CompactDuration {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompactDuration {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.duration.cmp(&other.duration)
+    }
+}
+
+impl Hash for CompactDuration {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.duration.hash(state);
+    }
+}
+
+impl From<Duration> for CompactDuration {
+    fn from(duration: Duration) -> Self {
+        CompactDuration {
+            duration,
+            spelling: None,
+        }
+    }
+}
+
+impl From<CompactDuration> for Duration {
+    fn from(duration: CompactDuration) -> Self {
+        duration.duration
+    }
+}
+
+impl FromStr for CompactDuration {
+    type Err = DurationError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let text = input.trim();
+        if text.is_empty() {
+            return Err(DurationError::Empty);
+        }
+
+        let mut total: u128 = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                return Err(DurationError::Malformed(input.to_string()));
+            }
+            let value: u128 = rest[..digits]
+                .parse()
+                .map_err(|_| DurationError::Overflow(input.to_string()))?;
+            rest = &rest[digits..];
+
+            let unit_len = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let unit = &rest[..unit_len];
+            rest = &rest[unit_len..];
+            if unit.is_empty() {
+                return Err(DurationError::Malformed(input.to_string()));
+            }
+            let nanos = UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, nanos)| *nanos)
+                .ok_or_else(|| DurationError::UnknownUnit {
+                    input: input.to_string(),
+                    unit: unit.to_string(),
+                })?;
+            total = value
+                .checked_mul(nanos)
+                .and_then(|segment| total.checked_add(segment))
+                .ok_or_else(|| DurationError::Overflow(input.to_string()))?;
+        }
+        let secs = u64::try_from(total / NANOS_PER_SEC)
+            .map_err(|_| DurationError::Overflow(input.to_string()))?;
+        Ok(CompactDuration {
+            duration: Duration::new(secs, (total % NANOS_PER_SEC) as u32),
+            spelling: Some(text.into()),
+        })
+    }
+}
+
+impl fmt::Display for CompactDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(spelling) = &self.spelling {
+            return f.write_str(spelling);
+        }
+        let mut nanos = self.duration.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0ms");
+        }
+        for (name, size) in UNITS {
+            if nanos >= size {
+                write!(f, "{}{}", nanos / size, name)?;
+                nanos %= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for CompactDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for CompactDuration {
+    fn schema_name() -> String {
+        "CompactDuration".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^([0-9]+(d|h|m|s|ms|us|ns))+$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+pub(super) mod rfc3339 {
+    use chrono::{DateTime, FixedOffset};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        at: &DateTime<FixedOffset>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&at.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<FixedOffset>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&text).map_err(|err| {
+            de::Error::custom(format!("invalid RFC 3339 timestamp '{}': {}", text, err))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> CompactDuration {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_fixture_durations() {
+        assert_eq!(parse("1h").as_duration(), Duration::from_secs(3600));
+        assert_eq!(parse("1m").as_duration(), Duration::from_secs(60));
+        assert_eq!(parse("234ms").as_duration(), Duration::from_millis(234));
+        assert_eq!(parse("1h30m").as_duration(), Duration::from_secs(5400));
+    }
+
+    #[test]
+    fn test_display_keeps_the_parsed_spelling() {
+        for text in [
+            "1h",
+            "1m",
+            "234ms",
+            "1h30m",
+            "2d",
+            "1m5s250ms",
+            "0ms",
+            "90m",
+            "0h5m",
+        ] {
+            assert_eq!(parse(text).to_string(), text);
+        }
+        assert_eq!(parse("90m"), parse("1h30m"));
+
+        let json = serde_json::to_string(&parse("90m")).unwrap();
+        assert_eq!(json, r#""90m""#);
+        let back: CompactDuration = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.to_string(), "90m");
+    }
+
+    #[test]
+    fn test_display_of_a_plain_duration_uses_compact_form() {
+        let display = |duration| CompactDuration::from(duration).to_string();
+        assert_eq!(display(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(display(Duration::from_millis(65_250)), "1m5s250ms");
+        assert_eq!(display(Duration::ZERO), "0ms");
+        assert_eq!(display(Duration::from_micros(500)), "500us");
+        assert_eq!(display(Duration::from_nanos(1_000_250)), "1ms250ns");
+    }
+
+    #[test]
+    fn test_sub_millisecond_durations_round_trip() {
+        for duration in [Duration::from_micros(500), Duration::new(90, 1)] {
+            let json = serde_json::to_string(&CompactDuration::from(duration)).unwrap();
+            let back: CompactDuration = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.as_duration(), duration, "{}", json);
+        }
+        assert_eq!(parse("1ms500us").as_duration(), Duration::from_micros(1500));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<CompactDuration>(), Err(DurationError::Empty));
+        assert_eq!(
+            "h".parse::<CompactDuration>(),
+            Err(DurationError::Malformed("h".to_string()))
+        );
+        assert_eq!(
+            "15".parse::<CompactDuration>(),
+            Err(DurationError::Malformed("15".to_string()))
+        );
+        assert_eq!(
+            "3w".parse::<CompactDuration>(),
+            Err(DurationError::UnknownUnit {
+                input: "3w".to_string(),
+                unit: "w".to_string(),
+            })
+        );
+    }
+}
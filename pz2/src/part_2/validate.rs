@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use super::{Request, Stream};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    errors: Vec<ValidationError>,
+}
+
+impl Collector {
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn positive_duration(&mut self, path: &str, duration: Duration) {
+        if duration.is_zero() {
+            self.error(path, "duration must be greater than zero");
+        }
+    }
+}
+
+impl Request {
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        self.check(false)
+    }
+
+    // Like `validate`, but also rejects settings bits that have no name.
+    pub fn validate_strict(&self) -> Result<(), ValidationErrors> {
+        self.check(true)
+    }
+
+    fn check(&self, strict: bool) -> Result<(), ValidationErrors> {
+        let mut check = Collector::default();
+
+        if let Some(stream) = self.stream() {
+            validate_stream(&mut check, stream);
+            if strict {
+                if let Err(err) = stream.settings.check_known() {
+                    check.error("stream.settings", err.to_string());
+                }
+            }
+        }
+
+        let mut seen: HashMap<u32, usize> = HashMap::new();
+        for (index, gift) in self.gifts().iter().enumerate() {
+            if let Some(first) = seen.insert(gift.id, index) {
+                check.error(
+                    format!("gifts[{}].id", index),
+                    format!(
+                        "duplicate gift id {}, first used by gifts[{}]",
+                        gift.id, first
+                    ),
+                );
+                seen.insert(gift.id, first);
+            }
+        }
+
+        if let Err(err) = self.gift_total() {
+            check.error("gifts", format!("cannot total gift prices: {}", err));
+        }
+
+        if let Some(failure) = self.failure() {
+            if failure.code().trim().is_empty() {
+                check.error("error.code", "error code must not be empty");
+            }
+        }
+
+        if check.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(check.errors))
+        }
+    }
+}
+
+fn validate_stream(check: &mut Collector, stream: &Stream) {
+    if let Err(err) = Uuid::parse_str(&stream.user_id) {
+        check.error("stream.user_id", format!("not a UUID: {}", err));
+    }
+
+    match Url::parse(&stream.shard_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        Ok(url) => check.error(
+            "stream.shard_url",
+            format!(
+                "unsupported scheme '{}', expected http or https",
+                url.scheme()
+            ),
+        ),
+        Err(err) => check.error("stream.shard_url", format!("not a URL: {}", err)),
+    }
+
+    check.positive_duration(
+        "stream.public_tariff.duration",
+        stream.public_tariff.duration(),
+    );
+    check.positive_duration(
+        "stream.private_tariff.duration",
+        stream.private_tariff.duration(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::part_2::fixture::{fixture, with};
+
+    #[test]
+    fn test_fixture_is_valid() {
+        let request = fixture();
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_every_problem_with_its_path() {
+        let request = with(&[
+            ("/stream/user_id", "not-a-uuid".into()),
+            ("/stream/shard_url", "n3.example.com/sapi".into()),
+            ("/stream/public_tariff/duration", "0ms".into()),
+            ("/gifts/1/id", 1.into()),
+        ]);
+        let errors = request.validate().unwrap_err();
+
+        let paths: Vec<_> = errors.0.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "stream.user_id",
+                "stream.shard_url",
+                "stream.public_tariff.duration",
+                "gifts[1].id",
+            ]
+        );
+        assert!(errors
+            .to_string()
+            .contains("gifts[1].id: duplicate gift id 1"));
+    }
+
+    #[test]
+    fn test_free_tariffs_and_gifts_are_valid() {
+        let request = with(&[
+            ("/stream/public_tariff/price", 0.into()),
+            ("/stream/private_tariff/client_price", 0.into()),
+            ("/gifts/0/price", 0.into()),
+        ]);
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_non_http_shard_url() {
+        let request = with(&[("/stream/shard_url", "ftp://n3.example.com/sapi".into())]);
+        let errors = request.validate().unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert!(errors.0[0].message.contains("unsupported scheme 'ftp'"));
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unknown_settings_bits() {
+        let request = with(&[("/stream/settings", 0.into())]);
+        assert_eq!(request.validate_strict(), Ok(()));
+
+        let request = fixture();
+        assert_eq!(request.validate(), Ok(()));
+        let errors = request.validate_strict().unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(
+            errors.to_string(),
+            "stream.settings: unknown settings bits 0xb121"
+        );
+    }
+
+    #[test]
+    fn test_rejects_gifts_in_mixed_currencies() {
+        let request = with(&[(
+            "/gifts/1/price",
+            serde_json::json!({ "amount": 3, "currency": "EUR" }),
+        )]);
+        let errors = request.validate().unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].path, "gifts");
+        assert!(errors.0[0].message.contains("cannot add EUR to USD"));
+    }
+}
@@ -0,0 +1,264 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    bracketed, parse_macro_input, Data, DeriveInput, Error, Fields, GenericParam, Ident, Token,
+    Type,
+};
+
+// Generates marker types and transition methods for a `Struct<State>` whose
+// state lives in a `PhantomData<State>` field:
+//
+//     #[derive(Typestate)]
+//     #[typestate(
+//         states = [Closed, Open],
+//         transitions = [Closed => Open: open, Open => Closed: close],
+//     )]
+//     struct Door<State> { name: String, state: PhantomData<State> }
+//
+// Every transition moves all other fields into the new state unchanged, and
+// `Door::<S>::STATE_GRAPH` holds the graph in Graphviz dot format. Adding
+// `markers = external` reuses marker types already in scope instead of
+// generating them, so several entities in one module can share a state name.
+#[proc_macro_derive(Typestate, attributes(typestate))]
+pub fn derive_typestate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Transition {
+    from: Ident,
+    to: Ident,
+    method: Ident,
+}
+
+impl Parse for Transition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let to = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let method = input.parse()?;
+        Ok(Transition { from, to, method })
+    }
+}
+
+struct Graph {
+    states: Vec<Ident>,
+    transitions: Vec<Transition>,
+    external_markers: bool,
+}
+
+impl Parse for Graph {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut states = None;
+        let mut transitions = None;
+        let mut external_markers = false;
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            if key == "markers" {
+                let value: Ident = input.parse()?;
+                external_markers = match value.to_string().as_str() {
+                    "generate" => false,
+                    "external" => true,
+                    _ => {
+                        return Err(Error::new(
+                            value.span(),
+                            "expected `generate` or `external`",
+                        ))
+                    }
+                };
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+            let content;
+            bracketed!(content in input);
+            match key.to_string().as_str() {
+                "states" => {
+                    let list = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                    states = Some(list.into_iter().collect());
+                }
+                "transitions" => {
+                    let list = Punctuated::<Transition, Token![,]>::parse_terminated(&content)?;
+                    transitions = Some(list.into_iter().collect());
+                }
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "expected `states`, `transitions` or `markers`",
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Graph {
+            states: states.ok_or_else(|| input.error("missing `states = [...]`"))?,
+            transitions: transitions.unwrap_or_default(),
+            external_markers,
+        })
+    }
+}
+
+impl Graph {
+    fn validate(&self) -> syn::Result<()> {
+        for (i, state) in self.states.iter().enumerate() {
+            if self.states[..i].contains(state) {
+                return Err(Error::new(state.span(), "state declared twice"));
+            }
+        }
+        for (i, t) in self.transitions.iter().enumerate() {
+            for state in [&t.from, &t.to] {
+                if !self.states.contains(state) {
+                    return Err(Error::new(state.span(), "unknown state"));
+                }
+            }
+            let clash = self.transitions[..i]
+                .iter()
+                .any(|other| other.from == t.from && other.method == t.method);
+            if clash {
+                return Err(Error::new(
+                    t.method.span(),
+                    "transition method already defined for this state",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_dot(&self, name: &Ident) -> String {
+        let mut dot = format!("digraph {} {{\n", name);
+        for state in &self.states {
+            dot.push_str(&format!("    {};\n", state));
+        }
+        for t in &self.transitions {
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{}\"];\n",
+                t.from, t.to, t.method
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn is_phantom_data(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "PhantomData"),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("typestate"))
+        .ok_or_else(|| Error::new(name.span(), "missing #[typestate(...)] attribute"))?;
+    let graph: Graph = attr.parse_args()?;
+    graph.validate()?;
+
+    let params: Vec<_> = input.generics.params.iter().collect();
+    if !matches!(params.as_slice(), [GenericParam::Type(_)]) {
+        return Err(Error::new(
+            name.span(),
+            "expected exactly one type parameter for the state",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    name.span(),
+                    "expected a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new(name.span(), "expected a struct")),
+    };
+
+    let mut phantom = None;
+    let mut carried = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        if is_phantom_data(&field.ty) {
+            phantom = Some(ident);
+        } else {
+            carried.push(ident);
+        }
+    }
+    let phantom =
+        phantom.ok_or_else(|| Error::new(name.span(), "expected a PhantomData<State> field"))?;
+
+    let generated = if graph.external_markers {
+        &[][..]
+    } else {
+        &graph.states[..]
+    };
+    let markers = generated.iter().map(|state| {
+        quote! {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #vis struct #state;
+        }
+    });
+
+    let methods = graph.states.iter().filter_map(|state| {
+        let outgoing: Vec<_> = graph
+            .transitions
+            .iter()
+            .filter(|t| t.from == *state)
+            .map(|t| {
+                let to = &t.to;
+                let method = &t.method;
+                quote! {
+                    #vis fn #method(self) -> #name<#to> {
+                        #name {
+                            #(#carried: self.#carried,)*
+                            #phantom: ::std::marker::PhantomData,
+                        }
+                    }
+                }
+            })
+            .collect();
+        if outgoing.is_empty() {
+            return None;
+        }
+        Some(quote! {
+            impl #name<#state> {
+                #(#outgoing)*
+            }
+        })
+    });
+
+    let dot = graph.to_dot(name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #(#markers)*
+
+        #(#methods)*
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub const STATE_GRAPH: &'static str = #dot;
+        }
+    })
+}
@@ -0,0 +1,122 @@
+use std::marker::PhantomData;
+use typestate_macros::Typestate;
+
+#[derive(Debug, Typestate)]
+#[typestate(
+    states = [Closed, Open, Locked],
+    transitions = [
+        Closed => Open: open,
+        Open => Closed: close,
+        Closed => Locked: lock,
+        Locked => Closed: unlock,
+    ]
+)]
+struct Door<State> {
+    name: String,
+    opened: u32,
+    state: PhantomData<State>,
+}
+
+impl Door<Closed> {
+    fn new(name: &str) -> Self {
+        Door {
+            name: name.to_string(),
+            opened: 0,
+            state: PhantomData,
+        }
+    }
+}
+
+// Shares `Closed` and `Open` with `Door` in the same module.
+#[derive(Debug, Typestate)]
+#[typestate(
+    states = [Closed, Open],
+    transitions = [Closed => Open: turn_on, Open => Closed: turn_off],
+    markers = external,
+)]
+struct Valve<State> {
+    flow: u32,
+    state: PhantomData<State>,
+}
+
+pub trait Powered {}
+
+#[derive(Debug, PartialEq)]
+struct Off;
+
+#[derive(Debug, PartialEq)]
+struct On;
+
+impl Powered for Off {}
+impl Powered for On {}
+
+// Bounds on the state parameter, inline and in a where-clause.
+#[derive(Debug, Typestate)]
+#[typestate(
+    states = [Off, On],
+    transitions = [Off => On: switch_on, On => Off: switch_off],
+    markers = external,
+)]
+struct Lamp<State: Powered>
+where
+    State: std::fmt::Debug,
+{
+    watts: u32,
+    state: PhantomData<State>,
+}
+
+#[test]
+fn test_generated_transitions_carry_fields() {
+    let door = Door::new("front");
+    let mut open: Door<Open> = door.open();
+    open.opened += 1;
+
+    let locked: Door<Locked> = open.close().lock();
+    assert_eq!(locked.name, "front");
+    assert_eq!(locked.opened, 1);
+
+    let closed: Door<Closed> = locked.unlock();
+    assert_eq!(closed.state, PhantomData::<Closed>);
+}
+
+#[test]
+fn test_marker_types_are_generated() {
+    assert_eq!(Closed, Closed);
+    assert_ne!(format!("{:?}", Open), format!("{:?}", Locked));
+}
+
+#[test]
+fn test_state_graph_is_exported_as_dot() {
+    assert_eq!(
+        Door::<Closed>::STATE_GRAPH,
+        "digraph Door {\n    Closed;\n    Open;\n    Locked;\n    \
+         Closed -> Open [label=\"open\"];\n    \
+         Open -> Closed [label=\"close\"];\n    \
+         Closed -> Locked [label=\"lock\"];\n    \
+         Locked -> Closed [label=\"unlock\"];\n}\n"
+    );
+}
+
+#[test]
+fn test_external_markers_are_shared_between_entities() {
+    let valve: Valve<Closed> = Valve {
+        flow: 3,
+        state: PhantomData,
+    };
+    let open: Valve<Open> = valve.turn_on();
+    assert_eq!(open.flow, 3);
+    assert_eq!(open.turn_off().state, PhantomData::<Closed>);
+    assert!(Valve::<Open>::STATE_GRAPH.starts_with("digraph Valve {"));
+}
+
+#[test]
+fn test_bounded_state_parameter_is_kept() {
+    let lamp: Lamp<Off> = Lamp {
+        watts: 60,
+        state: PhantomData,
+    };
+    let on: Lamp<On> = lamp.switch_on();
+    assert_eq!(on.watts, 60);
+    assert_eq!(on.switch_off().state, PhantomData::<Off>);
+    assert!(Lamp::<On>::STATE_GRAPH.starts_with("digraph Lamp {"));
+}